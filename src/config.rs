@@ -23,32 +23,669 @@
  * SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
  */
 
-use crate::core::command::Commands;
+use crate::core::border::{self, Borders};
+use crate::core::command::{Action, Commands};
 use crate::core::keys;
+use crate::core::layout::{Direction, Gaps, LayoutMessage};
+use crate::core::rule::Rule;
+use crate::core::x::Result;
+use crate::layouts;
+use serde::de::{self, Deserializer};
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
-pub struct Config<'a> {
+pub struct Config {
     modkey: keys::ModKey,
-    ctrl: &'a Commands,
+    workspaces: Vec<String>,
+    layouts: Vec<String>,
+    gaps: Gaps,
+    workspace_gaps: HashMap<String, Gaps>,
+    borders: Borders,
+    rules: Vec<Rule>,
+    mode_timeout: Duration,
+    ctrl: Commands,
 }
 
-impl<'a> Config<'a> {
-    pub fn new() -> Config<'a> {
+impl Config {
+    // The built-in config, without any keycombos.
+    pub fn new() -> Config {
         Config {
             // Set your ModKey here!
             modkey: keys::ModKey::Mod4,
-            ctrl: &Commands::new(),
+            // Set your workspaces here!
+            workspaces: ["1", "2", "3", "4", "5", "6", "7", "8", "9"]
+                .iter()
+                .map(|name| name.to_string())
+                .collect(),
+            // Set your layouts here! The first one is the default.
+            layouts: [
+                "tile", "stack", "grid", "spiral", "dwindle", "centered", "threecol", "bsp",
+            ]
+            .iter()
+            .map(|name| name.to_string())
+            .collect(),
+            // Set your gaps here! Outer, inner and whether to drop them for
+            // a single window.
+            gaps: Gaps::new(0, 0, true),
+            // Override them for single workspaces here!
+            workspace_gaps: HashMap::new(),
+            // Set your border width and colors here! Focused, unfocused,
+            // urgent and floating.
+            borders: Borders::new(1, "#005577", "#444444", "#ff0000", "#888888")
+                .expect("[E] Invalid border color."),
+            // Set your window rules here!
+            rules: Vec::new(),
+            // Set how long a keymap mode waits for the next key here!
+            mode_timeout: Duration::from_secs(5),
+            ctrl: Commands::new(),
         }
     }
 
-    pub fn get_ctrl(self) -> &'a Commands {
+    // Read the config file. Without one, or when it is broken, the
+    // built-in config is used.
+    pub fn load() -> Config {
+        Config::try_load().unwrap_or_else(|err| {
+            println!("{}\n[E] Using the built-in config.", err);
+            let mut conf = Config::new();
+            conf.wire();
+            conf
+        })
+    }
+
+    // Like `load`, but a broken config file is an error.
+    pub fn try_load() -> Result<Config> {
+        let path = Config::path();
+        if path.exists() {
+            return Config::from_file(&path);
+        }
+
+        let mut conf = Config::new();
+        conf.wire();
+        Ok(conf)
+    }
+
+    pub fn from_file(path: &Path) -> Result<Config> {
+        let text = fs::read_to_string(path).map_err(|err| {
+            failure::format_err!("[E] Could not read {}: {}", path.display(), err)
+        })?;
+        Config::parse(&text).map_err(|err| failure::format_err!("[E] {}: {}", path.display(), err))
+    }
+
+    // $XDG_CONFIG_HOME/void/config.toml, falling back to ~/.config.
+    pub fn path() -> PathBuf {
+        let base = env::var_os("XDG_CONFIG_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .unwrap_or_else(|| {
+                PathBuf::from(env::var_os("HOME").unwrap_or_default()).join(".config")
+            });
+
+        base.join("void").join("config.toml")
+    }
+
+    pub fn get_workspaces(&self) -> &[String] {
+        &self.workspaces
+    }
+
+    pub fn get_layouts(&self) -> &[String] {
+        &self.layouts
+    }
+
+    pub fn get_gaps(&self, workspace: &str) -> Gaps {
+        self.workspace_gaps
+            .get(workspace)
+            .copied()
+            .unwrap_or(self.gaps)
+    }
+
+    pub fn get_borders(&self) -> Borders {
+        self.borders
+    }
+
+    pub fn get_rules(&self) -> &[Rule] {
+        &self.rules
+    }
+
+    pub fn get_mode_timeout(&self) -> Duration {
+        self.mode_timeout
+    }
+
+    pub fn get_ctrl(&self) -> &Commands {
         &self.ctrl
     }
 
+    pub fn get_ctrl_mut(&mut self) -> &mut Commands {
+        &mut self.ctrl
+    }
+
+    // Private
+
+    fn parse(text: &str) -> std::result::Result<Config, String> {
+        let file: File = toml::from_str(text).map_err(|err| err.to_string())?;
+
+        let mut conf = Config::new();
+        file.check(&conf)?;
+        conf.apply(file);
+        Ok(conf)
+    }
+
+    // Whatever the file leaves out stays as built in. Its keycombos
+    // replace the built-in ones as a whole.
+    fn apply(&mut self, file: File) {
+        if let Some(modkey) = file.modkey {
+            self.modkey = modkey;
+        }
+        if let Some(workspaces) = file.workspaces {
+            self.workspaces = workspaces;
+        }
+        if let Some(layouts) = file.layouts {
+            self.layouts = layouts;
+        }
+        if let Some(gaps) = file.gaps {
+            self.gaps = gaps.apply(self.gaps);
+        }
+        for (name, gaps) in file.workspace_gaps {
+            self.workspace_gaps.insert(name, gaps.apply(self.gaps));
+        }
+        if let Some(borders) = file.borders {
+            self.borders = borders.apply(self.borders);
+        }
+        if let Some(timeout) = file.mode_timeout {
+            self.mode_timeout = Duration::from_secs(timeout);
+        }
+        self.rules = file.rules;
+
+        match file.bindings {
+            Some(bindings) => {
+                for binding in bindings {
+                    let keycombo = binding
+                        .key_combo(self.modkey)
+                        .expect("[E] Binding checked before applying.");
+                    self.ctrl
+                        .bind(binding.mode.as_deref(), keycombo, binding.action);
+                }
+            }
+            None => self.wire(),
+        }
+    }
+
     // Set your keycombos here!
     #[rustfmt::skip]
-    pub fn wire(self) {
-        self.ctrl.add(&[self.modkey], "enter", self.ctrl.exec,vec!["stc".to_string()]);
-        self.ctrl.add(&[self.modkey], "p", self.ctrl.exec, vec!["dmenu".to_string()]);
-        self.ctrl.add(&[self.modkey, keys::ModKey::Shift],"d",self.ctrl.exec, vec!["stc".to_string(), "-e".to_string(), "ranger".to_string()]);
+    fn wire(&mut self) {
+        self.add("$mod+enter", Action::Exec(vec!["stc".to_string()]));
+        self.add("$mod+p", Action::Exec(vec!["dmenu".to_string()]));
+        self.add("$mod+Shift+d", Action::Exec(vec!["stc".to_string(), "-e".to_string(), "ranger".to_string()]));
+        self.add("@Print", Action::Exec(vec!["scrot".to_string(), "-s".to_string()]));
+        self.add("$mod+Shift+c", Action::Kill);
+        self.add("$mod+Shift+q", Action::Quit);
+        self.add("$mod+Control+r", Action::Restart);
+        self.add("$mod+F5", Action::Reload);
+        self.add("$mod+j", Action::FocusNext);
+        self.add("$mod+k", Action::FocusPrev);
+        self.add("$mod+Up", Action::Focus(Direction::Up));
+        self.add("$mod+Down", Action::Focus(Direction::Down));
+        self.add("$mod+Shift+j", Action::SwapNext);
+        self.add("$mod+Shift+k", Action::SwapPrev);
+        self.add("$mod+Shift+h", Action::Swap(Direction::Left));
+        self.add("$mod+Shift+l", Action::Swap(Direction::Right));
+        self.add("$mod+Shift+space", Action::ToggleFloat);
+        self.add("$mod+t", Action::SetLayout("tile".to_string()));
+        self.add("$mod+m", Action::SetLayout("stack".to_string()));
+        self.add("$mod+g", Action::SetLayout("grid".to_string()));
+        self.add("$mod+b", Action::SetLayout("bsp".to_string()));
+        self.add("$mod+space", Action::NextLayout);
+        self.add("$mod+Control+Tab", Action::PrevLayout);
+        self.add("$mod+l", Action::IncMsize(5));
+        self.add("$mod+h", Action::IncMsize(-5));
+        self.add("$mod+i", Action::IncNmaster(1));
+        self.add("$mod+d", Action::IncNmaster(-1));
+        self.add("$mod+Control+h", Action::Layout(LayoutMessage::Preselect(Direction::Left, 50)));
+        self.add("$mod+Control+j", Action::Layout(LayoutMessage::Preselect(Direction::Down, 50)));
+        self.add("$mod+Control+k", Action::Layout(LayoutMessage::Preselect(Direction::Up, 50)));
+        self.add("$mod+Control+l", Action::Layout(LayoutMessage::Preselect(Direction::Right, 50)));
+        self.add("$mod+Control+space", Action::Layout(LayoutMessage::CancelPreselect));
+        self.add("$mod+Mod1+h", Action::Layout(LayoutMessage::ResizeSplit(Direction::Left, 5)));
+        self.add("$mod+Mod1+j", Action::Layout(LayoutMessage::ResizeSplit(Direction::Down, 5)));
+        self.add("$mod+Mod1+k", Action::Layout(LayoutMessage::ResizeSplit(Direction::Up, 5)));
+        self.add("$mod+Mod1+l", Action::Layout(LayoutMessage::ResizeSplit(Direction::Right, 5)));
+        self.add("$mod+r", Action::Layout(LayoutMessage::Rotate));
+        self.add("$mod+Shift+r", Action::Layout(LayoutMessage::FlipHorizontal));
+        self.add("$mod+equal", Action::IncGaps(2, 2));
+        self.add("$mod+minus", Action::IncGaps(-2, -2));
+        self.add("$mod+Shift+equal", Action::IncGaps(2, 0));
+        self.add("$mod+Shift+minus", Action::IncGaps(-2, 0));
+        self.add("$mod+Control+equal", Action::IncGaps(0, 2));
+        self.add("$mod+Control+minus", Action::IncGaps(0, -2));
+        self.add("$mod+0", Action::ResetGaps);
+        self.add("$mod+Right", Action::NextWorkspace);
+        self.add("$mod+Left", Action::PrevWorkspace);
+
+        self.add("$mod+s", Action::Mode("resize".to_string()));
+        self.add_to("resize", "h", Action::IncMsize(-5));
+        self.add_to("resize", "l", Action::IncMsize(5));
+        self.add_to("resize", "j", Action::Layout(LayoutMessage::ResizeSplit(Direction::Down, 5)));
+        self.add_to("resize", "k", Action::Layout(LayoutMessage::ResizeSplit(Direction::Up, 5)));
+        self.add_to("resize", "equal", Action::IncGaps(2, 2));
+        self.add_to("resize", "minus", Action::IncGaps(-2, -2));
+        self.add_to("resize", "enter", Action::ExitMode);
+
+        self.add("$mod+x", Action::Chord("launch".to_string()));
+        self.add_to("launch", "t", Action::Exec(vec!["stc".to_string()]));
+        self.add_to("launch", "f", Action::Exec(vec!["firefox".to_string()]));
+        self.add_to("launch", "r", Action::Exec(vec!["stc".to_string(), "-e".to_string(), "ranger".to_string()]));
+
+        for idx in 0..self.workspaces.len() {
+            self.add(&format!("$mod+{}", idx + 1), Action::Workspace(idx));
+            self.add(&format!("$mod+Shift+{}", idx + 1), Action::MoveToWorkspace(idx));
+        }
+    }
+
+    // Bind `action` to a keycombo like "$mod+Shift+d", or to letting go of
+    // the key, like "@Print".
+    fn add(&mut self, text: &str, action: Action) {
+        if let Some(keycombo) = self.combo(text) {
+            self.ctrl.bind(None, keycombo, action);
+        }
+    }
+
+    // Same as `add`, for the keymap of the named mode.
+    fn add_to(&mut self, mode: &str, text: &str, action: Action) {
+        if let Some(keycombo) = self.combo(text) {
+            self.ctrl.bind(Some(mode), keycombo, action);
+        }
+    }
+
+    fn combo(&self, text: &str) -> Option<keys::KeyCombo> {
+        match keys::KeyCombo::parse(text, self.modkey) {
+            Ok(keycombo) => Some(keycombo),
+            Err(err) => {
+                println!("[E] {}, binding skipped.", err);
+                None
+            }
+        }
+    }
+}
+
+// The config file. Everything in it is optional.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct File {
+    #[serde(default, deserialize_with = "de_modkey")]
+    modkey: Option<keys::ModKey>,
+    workspaces: Option<Vec<String>>,
+    #[serde(default, deserialize_with = "de_layouts")]
+    layouts: Option<Vec<String>>,
+    gaps: Option<FileGaps>,
+    #[serde(default)]
+    workspace_gaps: HashMap<String, FileGaps>,
+    borders: Option<FileBorders>,
+    // In seconds.
+    mode_timeout: Option<u64>,
+    #[serde(default, rename = "rule")]
+    rules: Vec<Rule>,
+    #[serde(rename = "bind")]
+    bindings: Option<Vec<FileBinding>>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FileGaps {
+    outer: Option<u16>,
+    inner: Option<u16>,
+    smart: Option<bool>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FileBorders {
+    width: Option<u32>,
+    #[serde(default, deserialize_with = "de_color")]
+    focused: Option<u32>,
+    #[serde(default, deserialize_with = "de_color")]
+    unfocused: Option<u32>,
+    #[serde(default, deserialize_with = "de_color")]
+    urgent: Option<u32>,
+    #[serde(default, deserialize_with = "de_color")]
+    floating: Option<u32>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FileBinding {
+    // Checked along with the other bindings, so errors can name the
+    // binding. Inside an array of tables toml can only point at its
+    // header.
+    #[serde(default)]
+    mods: Vec<String>,
+    // A single key or a whole keycombo like "$mod+Shift+Return".
+    key: String,
+    action: Action,
+    #[serde(default)]
+    release: bool,
+    // Bind into the keymap of this mode instead of the global one.
+    mode: Option<String>,
+}
+
+impl File {
+    // What the types alone cannot tell. `defaults` fills in what the
+    // file leaves out.
+    fn check(&self, defaults: &Config) -> std::result::Result<(), String> {
+        if self.workspaces.as_ref().is_some_and(Vec::is_empty) {
+            return Err("at least one workspace is needed".to_string());
+        }
+
+        let modkey = self.modkey.unwrap_or(defaults.modkey);
+        let workspaces = self
+            .workspaces
+            .as_ref()
+            .unwrap_or(&defaults.workspaces)
+            .len();
+        // Both sides by the name the layout gives itself, as the
+        // spelling in the file may differ.
+        let layout_name =
+            |name: &str| layouts::from_name(name).map(|layout| layout.name().to_string());
+        let layouts: Vec<String> = self
+            .layouts
+            .as_ref()
+            .unwrap_or(&defaults.layouts)
+            .iter()
+            .filter_map(|name| layout_name(name))
+            .collect();
+
+        let bindings = self.bindings.as_deref().unwrap_or_default();
+        let modes: HashSet<&str> = bindings
+            .iter()
+            .filter_map(|binding| binding.mode.as_deref())
+            .collect();
+        let mut seen = HashMap::new();
+
+        for (idx, binding) in bindings.iter().enumerate() {
+            let keycombo = binding
+                .key_combo(modkey)
+                .map_err(|err| format!("binding {}: {}", idx + 1, err))?;
+            if let Some(first) = seen.insert((binding.mode.as_deref(), keycombo.clone()), idx) {
+                return Err(format!(
+                    "`{}` is bound twice, in bindings {} and {}",
+                    keycombo,
+                    first + 1,
+                    idx + 1
+                ));
+            }
+
+            match &binding.action {
+                Action::Mode(name) | Action::Chord(name) if !modes.contains(name.as_str()) => {
+                    return Err(format!(
+                        "binding {}: no key is bound in mode `{}`",
+                        idx + 1,
+                        name
+                    ));
+                }
+                Action::SetLayout(name) => match layout_name(name) {
+                    Some(normal) if layouts.contains(&normal) => {}
+                    Some(_) => {
+                        return Err(format!(
+                            "binding {}: layout `{}` is not in the layouts list",
+                            idx + 1,
+                            name
+                        ));
+                    }
+                    None => return Err(format!("binding {}: unknown layout `{}`", idx + 1, name)),
+                },
+                Action::Workspace(ws) | Action::MoveToWorkspace(ws) if *ws >= workspaces => {
+                    return Err(format!(
+                        "binding {}: there is no workspace {}, only {}",
+                        idx + 1,
+                        ws,
+                        workspaces
+                    ));
+                }
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl FileGaps {
+    fn apply(&self, gaps: Gaps) -> Gaps {
+        Gaps::new(
+            self.outer.unwrap_or(gaps.outer),
+            self.inner.unwrap_or(gaps.inner),
+            self.smart.unwrap_or(gaps.smart),
+        )
+    }
+}
+
+impl FileBorders {
+    fn apply(&self, borders: Borders) -> Borders {
+        Borders {
+            width: self.width.unwrap_or(borders.width),
+            focused: self.focused.unwrap_or(borders.focused),
+            unfocused: self.unfocused.unwrap_or(borders.unfocused),
+            urgent: self.urgent.unwrap_or(borders.urgent),
+            floating: self.floating.unwrap_or(borders.floating),
+        }
+    }
+}
+
+impl FileBinding {
+    fn key_combo(&self, modkey: keys::ModKey) -> std::result::Result<keys::KeyCombo, String> {
+        let mut keycombo = keys::KeyCombo::parse(&self.key, modkey)?;
+        for name in &self.mods {
+            let mod_key = match name.as_str() {
+                "$mod" => modkey,
+                name => keys::ModKey::from_name(name)
+                    .ok_or_else(|| format!("unknown modifier `{}` in mods", name))?,
+            };
+            keycombo.modmask |= mod_key.mask();
+        }
+
+        if self.release {
+            Ok(keycombo.on_release())
+        } else {
+            Ok(keycombo)
+        }
+    }
+}
+
+// Names are checked while parsing, so errors point at the line.
+
+fn de_modkey<'de, D>(deserializer: D) -> std::result::Result<Option<keys::ModKey>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let name = String::deserialize(deserializer)?;
+    keys::ModKey::from_name(&name)
+        .map(Some)
+        .ok_or_else(|| de::Error::custom(format!("unknown modifier `{}`", name)))
+}
+
+fn de_layouts<'de, D>(deserializer: D) -> std::result::Result<Option<Vec<String>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let names = Vec::<String>::deserialize(deserializer)?;
+    match names.iter().find(|name| layouts::from_name(name).is_none()) {
+        Some(name) => Err(de::Error::custom(format!("unknown layout `{}`", name))),
+        None => Ok(Some(names)),
+    }
+}
+
+fn de_color<'de, D>(deserializer: D) -> std::result::Result<Option<u32>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let hex = String::deserialize(deserializer)?;
+    border::parse_color(&hex)
+        .map(Some)
+        .map_err(|_| de::Error::custom(format!("invalid color `{}`, expected #rrggbb", hex)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error(text: &str) -> String {
+        match Config::parse(text) {
+            Ok(_) => panic!("accepted:\n{}", text),
+            Err(err) => err,
+        }
+    }
+
+    #[test]
+    fn accepts_layouts_however_spaced() {
+        let conf = Config::parse(
+            r#"
+            layouts = ["gaps(8,tile)"]
+
+            [[bind]]
+            key = "$mod+t"
+            action = { set_layout = "gaps( 8, tile )" }
+            "#,
+        )
+        .unwrap();
+        assert_eq!(conf.get_layouts(), ["gaps(8,tile)"]);
+    }
+
+    #[test]
+    fn rejects_empty_workspaces() {
+        assert_eq!(error("workspaces = []"), "at least one workspace is needed");
+    }
+
+    #[test]
+    fn rejects_duplicates() {
+        let err = error(
+            r#"
+            [[bind]]
+            key = "$mod+Return"
+            action = "kill"
+
+            [[bind]]
+            key = "Return"
+            mods = ["$mod"]
+            action = "quit"
+            "#,
+        );
+        assert_eq!(err, "`Mod4+Return` is bound twice, in bindings 1 and 2");
+    }
+
+    #[test]
+    fn allows_the_same_key_in_other_modes() {
+        Config::parse(
+            r#"
+            [[bind]]
+            key = "$mod+r"
+            action = { mode = "resize" }
+
+            [[bind]]
+            key = "$mod+r"
+            action = "exit_mode"
+            mode = "resize"
+            "#,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn rejects_unknown_modes() {
+        let err = error(
+            r#"
+            [[bind]]
+            key = "$mod+r"
+            action = { chord = "launch" }
+            "#,
+        );
+        assert_eq!(err, "binding 1: no key is bound in mode `launch`");
+    }
+
+    #[test]
+    fn rejects_unlisted_layouts() {
+        let err = error(
+            r#"
+            layouts = ["tile"]
+
+            [[bind]]
+            key = "$mod+b"
+            action = { set_layout = "bsp" }
+            "#,
+        );
+        assert_eq!(err, "binding 1: layout `bsp` is not in the layouts list");
+
+        let err = error(
+            r#"
+            [[bind]]
+            key = "$mod+b"
+            action = { set_layout = "nosuch" }
+            "#,
+        );
+        assert_eq!(err, "binding 1: unknown layout `nosuch`");
+    }
+
+    #[test]
+    fn rejects_missing_workspaces() {
+        let err = error(
+            r#"
+            [[bind]]
+            key = "$mod+x"
+            action = { workspace = 42 }
+            "#,
+        );
+        assert_eq!(err, "binding 1: there is no workspace 42, only 9");
+
+        let err = error(
+            r#"
+            workspaces = ["a", "b"]
+
+            [[bind]]
+            key = "$mod+a"
+            action = { move_to_workspace = 1 }
+
+            [[bind]]
+            key = "$mod+b"
+            action = { move_to_workspace = 2 }
+            "#,
+        );
+        assert_eq!(err, "binding 2: there is no workspace 2, only 2");
+    }
+
+    #[test]
+    fn names_the_binding_of_bad_keys() {
+        let binding = |key: &str, mods: &str| {
+            format!(
+                r#"
+                [[bind]]
+                key = "$mod+q"
+                action = "kill"
+
+                [[bind]]
+                key = "{}"
+                mods = [{}]
+                action = "quit"
+                "#,
+                key, mods
+            )
+        };
+
+        assert_eq!(
+            error(&binding("$mod+nosuch", "")),
+            "binding 2: unknown key `nosuch` in `$mod+nosuch`"
+        );
+        assert_eq!(
+            error(&binding("Hyper+x", "")),
+            "binding 2: unknown modifier `Hyper` in `Hyper+x`"
+        );
+        assert_eq!(
+            error(&binding("x", r#""Hyper""#)),
+            "binding 2: unknown modifier `Hyper` in mods"
+        );
+        assert_eq!(
+            error(&binding("$mod+", "")),
+            "binding 2: missing key in `$mod+`"
+        );
     }
 }
@@ -0,0 +1,64 @@
+/*
+ * Copyright (c) 2020, Florian Büstgens
+ * All rights reserved.
+ *
+ * Redistribution and use in source and binary forms, with or without
+ * modification, are permitted provided that the following conditions are met:
+ *     1. Redistributions of source code must retain the above copyright
+ *        notice, this list of conditions and the following disclaimer.
+ *
+ *     2. Redistributions in binary form must reproduce the above copyright notice,
+ *        this list of conditions and the following disclaimer in the
+ *        documentation and/or other materials provided with the distribution.
+ *
+ * THIS SOFTWARE IS PROVIDED BY <copyright holder> ''AS IS'' AND ANY
+ * EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+ * WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+ * DISCLAIMED. IN NO EVENT SHALL <copyright holder> BE LIABLE FOR ANY
+ * DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+ * (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+ * LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND
+ * ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+ * (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+ * SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+ */
+// crate
+use crate::core::x::Result;
+
+// Structs
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Borders {
+    pub width: u32,
+    pub focused: u32,
+    pub unfocused: u32,
+    pub urgent: u32,
+    pub floating: u32,
+}
+
+// Impl
+
+impl Borders {
+    // Colors are given as "#rrggbb".
+    pub fn new(
+        width: u32,
+        focused: &str,
+        unfocused: &str,
+        urgent: &str,
+        floating: &str,
+    ) -> Result<Borders> {
+        Ok(Borders {
+            width,
+            focused: parse_color(focused)?,
+            unfocused: parse_color(unfocused)?,
+            urgent: parse_color(urgent)?,
+            floating: parse_color(floating)?,
+        })
+    }
+}
+
+pub fn parse_color(hex: &str) -> Result<u32> {
+    hex.strip_prefix('#')
+        .filter(|digits| digits.len() == 6 && digits.chars().all(|c| c.is_ascii_hexdigit()))
+        .and_then(|digits| u32::from_str_radix(digits, 16).ok())
+        .ok_or_else(|| failure::format_err!("[E] Invalid color {}, expected #rrggbb", hex))
+}
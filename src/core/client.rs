@@ -0,0 +1,528 @@
+/*
+ * Copyright (c) 2020, Florian Büstgens
+ * All rights reserved.
+ *
+ * Redistribution and use in source and binary forms, with or without
+ * modification, are permitted provided that the following conditions are met:
+ *     1. Redistributions of source code must retain the above copyright
+ *        notice, this list of conditions and the following disclaimer.
+ *
+ *     2. Redistributions in binary form must reproduce the above copyright notice,
+ *        this list of conditions and the following disclaimer in the
+ *        documentation and/or other materials provided with the distribution.
+ *
+ * THIS SOFTWARE IS PROVIDED BY <copyright holder> ''AS IS'' AND ANY
+ * EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+ * WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+ * DISCLAIMED. IN NO EVENT SHALL <copyright holder> BE LIABLE FOR ANY
+ * DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+ * (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+ * LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND
+ * ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+ * (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+ * SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+ */
+// crate
+use crate::config::Config;
+use crate::core::border::Borders;
+use crate::core::layout::{Direction, Geometry, Layout, LayoutMessage};
+use crate::core::rule::Rule;
+use crate::core::workspace::WorkspaceSet;
+use crate::core::x::{Connection, Window, WindowState, WindowType};
+use crate::layouts;
+use crate::layouts::tile::TileLayout;
+
+// std
+use std::collections::HashMap;
+
+// Structs
+#[derive(Default)]
+pub struct Client {
+    // Where the client goes when it floats again.
+    float_geometry: Option<Geometry>,
+    urgent: bool,
+}
+
+pub struct ClientManager<'a> {
+    conn: &'a Connection,
+    clients: HashMap<Window, Client>,
+    workspaces: WorkspaceSet,
+    borders: Borders,
+    rules: Vec<Rule>,
+    // Names the layouts were built from, to tell whether a reload
+    // changed them.
+    layout_names: Vec<String>,
+    // Unmaps we caused ourselves, e.g. by switching workspaces.
+    ignore_unmaps: HashMap<Window, u32>,
+}
+
+// Impl
+
+impl<'a> ClientManager<'a> {
+    pub fn new(conn: &'a Connection, conf: &Config) -> ClientManager<'a> {
+        ClientManager {
+            conn,
+            clients: HashMap::new(),
+            workspaces: WorkspaceSet::new(
+                conf.get_workspaces(),
+                || ClientManager::layouts(conf.get_layouts()),
+                |name| conf.get_gaps(name),
+            ),
+            borders: conf.get_borders(),
+            rules: conf.get_rules().to_vec(),
+            layout_names: conf.get_layouts().to_vec(),
+            ignore_unmaps: HashMap::new(),
+        }
+    }
+
+    pub fn is_managed(&self, win: &Window) -> bool {
+        self.clients.contains_key(win)
+    }
+
+    // Adopt a window that asked to be mapped.
+    pub fn manage(&mut self, win: Window) {
+        if self.is_managed(&win) {
+            // Hidden workspaces keep their windows unmapped.
+            if self.workspaces.find(&win) == Some(self.workspaces.index()) {
+                self.conn.window_map(&win);
+            }
+            return;
+        }
+
+        let mut client = Client::default();
+        let (floating, idx) = self.rules_for(&win);
+        let floating = floating.unwrap_or_else(|| self.wants_floating(&win));
+        let idx = idx.unwrap_or_else(|| self.workspaces.index());
+        if floating {
+            // Gone before we got to it.
+            match self.conn.window_geometry(&win) {
+                Some(geo) => client.float_geometry = Some(geo),
+                None => return,
+            }
+        }
+
+        self.conn.window_enable_tracking(&win);
+        self.clients.insert(win, client);
+
+        if idx != self.workspaces.index() {
+            // Windows found at startup are mapped already.
+            if self.conn.window_is_viewable(&win) {
+                *self.ignore_unmaps.entry(win).or_insert(0) += 1;
+                self.conn.window_unmap(&win);
+            }
+            if let Some(ws) = self.workspaces.get_mut(idx) {
+                ws.add(win, floating);
+            }
+            return;
+        }
+
+        self.conn.window_map(&win);
+        self.workspaces.current_mut().add(win, floating);
+        self.render();
+        self.focus_current();
+    }
+
+    // Take over windows that were already mapped before we started.
+    pub fn adopt(&mut self, wins: Vec<Window>) {
+        for win in wins {
+            if self.conn.window_is_adoptable(&win) {
+                self.manage(win);
+            }
+        }
+    }
+
+    // The client withdrew its window, but the window still exists.
+    pub fn withdraw(&mut self, win: &Window) {
+        if let Some(count) = self.ignore_unmaps.get_mut(win) {
+            *count -= 1;
+            if *count == 0 {
+                self.ignore_unmaps.remove(win);
+            }
+            return;
+        }
+
+        self.unmanage(win);
+    }
+
+    // Let go of a window that is still around, but no client anymore.
+    pub fn unmanage(&mut self, win: &Window) {
+        if self.is_managed(win) {
+            self.conn.window_disable_tracking(win);
+            self.forget(win);
+        }
+    }
+
+    // The window is gone, just drop every reference to it.
+    pub fn forget(&mut self, win: &Window) {
+        self.ignore_unmaps.remove(win);
+
+        if self.clients.remove(win).is_none() {
+            return;
+        }
+
+        if let Some(idx) = self.workspaces.find(win) {
+            if let Some(ws) = self.workspaces.get_mut(idx) {
+                ws.remove(win);
+            }
+
+            if idx == self.workspaces.index() {
+                self.render();
+                self.focus_current();
+            }
+        }
+    }
+
+    pub fn focus(&mut self, win: &Window) {
+        if self.is_managed(win) {
+            self.workspaces.current_mut().focus(win);
+            self.focus_current();
+        }
+    }
+
+    pub fn focus_next(&mut self) {
+        self.workspaces.current_mut().focus_next();
+        self.focus_current();
+    }
+
+    pub fn focus_prev(&mut self) {
+        self.workspaces.current_mut().focus_prev();
+        self.focus_current();
+    }
+
+    pub fn focus_direction(&mut self, dir: Direction) {
+        if let Some(win) = self.neighbour(dir, false) {
+            self.focus(&win);
+        }
+    }
+
+    pub fn swap_next(&mut self) {
+        self.workspaces.current_mut().swap_next();
+        self.render();
+    }
+
+    pub fn swap_prev(&mut self) {
+        self.workspaces.current_mut().swap_prev();
+        self.render();
+    }
+
+    pub fn swap_direction(&mut self, dir: Direction) {
+        let focused = self.workspaces.current().focused().copied();
+        if let (Some(focused), Some(win)) = (focused, self.neighbour(dir, true)) {
+            self.workspaces.current_mut().swap(&focused, &win);
+            self.render();
+        }
+    }
+
+    // Ask the focused client to close, it gets destroyed if it does not
+    // know how to.
+    pub fn kill(&self) {
+        if let Some(win) = self.workspaces.current().focused() {
+            self.conn.window_close(win);
+        }
+    }
+
+    // Map everything again before we go, so the next wm finds it.
+    pub fn release(&self) {
+        for win in self.clients.keys() {
+            self.conn.window_map(win);
+        }
+        self.conn.flush();
+    }
+
+    pub fn switch_workspace(&mut self, idx: usize) {
+        if idx == self.workspaces.index() || idx >= self.workspaces.len() {
+            return;
+        }
+
+        for win in self.workspaces.current().clients() {
+            *self.ignore_unmaps.entry(*win).or_insert(0) += 1;
+        }
+        self.workspaces.current().hide(self.conn);
+
+        self.workspaces.select(idx);
+        self.workspaces.current().show(self.conn);
+        self.render();
+        self.focus_current();
+    }
+
+    pub fn next_workspace(&mut self) {
+        self.switch_workspace(self.workspaces.next_index());
+    }
+
+    pub fn prev_workspace(&mut self) {
+        self.switch_workspace(self.workspaces.prev_index());
+    }
+
+    // Send the focused window to another workspace.
+    pub fn move_to_workspace(&mut self, idx: usize) {
+        if idx == self.workspaces.index() || idx >= self.workspaces.len() {
+            return;
+        }
+
+        let win = match self.workspaces.current().focused() {
+            Some(win) => *win,
+            None => return,
+        };
+
+        let floating = self.workspaces.current().is_floating(&win);
+        self.workspaces.current_mut().remove(&win);
+        *self.ignore_unmaps.entry(win).or_insert(0) += 1;
+        self.conn.window_unmap(&win);
+
+        if let Some(ws) = self.workspaces.get_mut(idx) {
+            ws.add(win, floating);
+        }
+
+        self.render();
+        self.focus_current();
+    }
+
+    pub fn toggle_float(&mut self) {
+        let win = match self.workspaces.current().focused() {
+            Some(win) => *win,
+            None => return,
+        };
+        let floating = !self.workspaces.current().is_floating(&win);
+
+        if let Some(client) = self.clients.get_mut(&win) {
+            if floating {
+                if let Some(geo) = client.float_geometry {
+                    self.conn.window_configure(&win, &geo);
+                }
+            } else {
+                match self.conn.window_geometry(&win) {
+                    Some(geo) => client.float_geometry = Some(geo),
+                    None => return,
+                }
+            }
+        }
+
+        self.workspaces.current_mut().set_floating(&win, floating);
+        self.render();
+        self.focus_current();
+    }
+
+    pub fn set_layout(&mut self, name: &str) {
+        // Layouts go by their own spelling, `gaps(8, tile)` for `gaps(8,tile)`.
+        let name = match layouts::from_name(name) {
+            Some(layout) => layout.name().to_string(),
+            None => return,
+        };
+        if self.workspaces.current_mut().set_layout(&name) {
+            self.render();
+        }
+    }
+
+    pub fn next_layout(&mut self) {
+        self.workspaces.current_mut().next_layout();
+        self.render();
+    }
+
+    pub fn prev_layout(&mut self) {
+        self.workspaces.current_mut().prev_layout();
+        self.render();
+    }
+
+    pub fn inc_gaps(&mut self, outer: i16, inner: i16) {
+        self.workspaces.current_mut().inc_gaps(outer, inner);
+        self.render();
+    }
+
+    pub fn reset_gaps(&mut self) {
+        self.workspaces.current_mut().reset_gaps();
+        self.render();
+    }
+
+    pub fn inc_msize(&mut self, size: i16) {
+        self.workspaces.current_mut().layout_mut().inc_msize(size);
+        self.render();
+    }
+
+    pub fn inc_ssize(&mut self, size: i16) {
+        self.workspaces.current_mut().layout_mut().inc_ssize(size);
+        self.render();
+    }
+
+    pub fn inc_nmaster(&mut self, count: i16) {
+        self.workspaces
+            .current_mut()
+            .layout_mut()
+            .inc_nmaster(count);
+        self.render();
+    }
+
+    pub fn layout_message(&mut self, msg: &LayoutMessage) {
+        if self.workspaces.current_mut().layout_message(msg) {
+            self.render();
+        }
+    }
+
+    // The client changed its hints, it may ask for attention now.
+    pub fn update_urgency(&mut self, win: &Window) {
+        let focused = self.workspaces.current().focused() == Some(win);
+        let urgent = !focused && self.conn.window_is_urgent(win);
+
+        if let Some(client) = self.clients.get_mut(win) {
+            client.urgent = urgent;
+            self.paint();
+        }
+    }
+
+    // Take over a reloaded config. Windows stay where they are, hidden
+    // workspaces pick up the changes when they are shown again.
+    pub fn reconfigure(&mut self, conf: &Config) {
+        self.borders = conf.get_borders();
+        self.rules = conf.get_rules().to_vec();
+
+        if self.workspaces.names() != conf.get_workspaces() {
+            println!("[E] Changed workspaces need a restart.");
+        }
+
+        let relayout = self.layout_names.as_slice() != conf.get_layouts();
+        for ws in self.workspaces.iter_mut() {
+            ws.set_default_gaps(conf.get_gaps(ws.name()));
+            if relayout {
+                ws.set_layouts(ClientManager::layouts(conf.get_layouts()));
+            }
+        }
+        self.layout_names = conf.get_layouts().to_vec();
+
+        self.render();
+    }
+
+    pub fn render(&self) {
+        let ws = self.workspaces.current();
+        ws.render(self.conn, &self.conn.screen_geometry(), self.borders.width);
+        self.paint();
+        self.restack();
+    }
+
+    // Private
+
+    // Border colors by priority: urgent, focused, floating, unfocused.
+    fn paint(&self) {
+        let ws = self.workspaces.current();
+
+        for win in ws.clients() {
+            let urgent = self.clients.get(win).is_some_and(|client| client.urgent);
+            let pixel = if urgent {
+                self.borders.urgent
+            } else if ws.focused() == Some(win) {
+                self.borders.focused
+            } else if ws.is_floating(win) {
+                self.borders.floating
+            } else {
+                self.borders.unfocused
+            };
+
+            if ws.is_floating(win) {
+                self.conn.window_set_border_width(win, self.borders.width);
+            }
+            self.conn.window_set_border_color(win, pixel);
+        }
+    }
+
+    // Focused tiled window on top of the layout, floating windows above
+    // everything, the focused one last.
+    fn restack(&self) {
+        let ws = self.workspaces.current();
+        let focused = ws.focused();
+
+        if let Some(win) = focused.filter(|win| !ws.is_floating(win)) {
+            self.conn.window_raise(win);
+        }
+
+        for win in ws.floating().iter().filter(|&win| Some(win) != focused) {
+            self.conn.window_raise(win);
+        }
+
+        if let Some(win) = focused.filter(|win| ws.is_floating(win)) {
+            self.conn.window_raise(win);
+        }
+    }
+
+    fn focus_current(&mut self) {
+        match self.workspaces.current().focused().copied() {
+            Some(win) => {
+                if let Some(client) = self.clients.get_mut(&win) {
+                    client.urgent = false;
+                }
+                self.paint();
+                self.restack();
+                self.conn.window_focus(&win);
+            }
+            None => self.conn.window_unfocus(),
+        }
+    }
+
+    // The client next to the focused one on screen, `tiled` skips the
+    // floating layer. Windows already gone are left out.
+    fn neighbour(&self, dir: Direction, tiled: bool) -> Option<Window> {
+        let ws = self.workspaces.current();
+        let focused = self.conn.window_geometry(ws.focused()?)?;
+        let wins = if tiled {
+            ws.tiled()
+        } else {
+            ws.clients().to_vec()
+        };
+        let (wins, geos): (Vec<Window>, Vec<Geometry>) = wins
+            .into_iter()
+            .filter_map(|win| Some((win, self.conn.window_geometry(&win)?)))
+            .unzip();
+
+        focused.nearest(dir, &geos).map(|idx| wins[idx])
+    }
+
+    // Unknown names are skipped, tiling is the fallback.
+    fn layouts(names: &[String]) -> Vec<Box<dyn Layout>> {
+        let mut layouts: Vec<Box<dyn Layout>> = names
+            .iter()
+            .filter_map(|name| layouts::from_name(name))
+            .collect();
+
+        if layouts.is_empty() {
+            layouts.push(Box::new(TileLayout::new()));
+        }
+
+        layouts
+    }
+
+    // Floating state and workspace index asked for by the rules, later
+    // rules win.
+    fn rules_for(&self, win: &Window) -> (Option<bool>, Option<usize>) {
+        let (instance, class) = self.conn.window_class(win);
+        let title = self.conn.window_title(win);
+        let mut floating = None;
+        let mut idx = None;
+
+        for rule in &self.rules {
+            if !rule.matches(&class, &instance, &title) {
+                continue;
+            }
+            floating = rule.floating.or(floating);
+            idx = rule
+                .workspace
+                .as_ref()
+                .and_then(|name| self.workspaces.position(name))
+                .or(idx);
+        }
+
+        (floating, idx)
+    }
+
+    // Dialogs, modal and other transient windows float from the start.
+    fn wants_floating(&self, win: &Window) -> bool {
+        self.conn.window_transient_for(win).is_some()
+            || self.conn.window_states(win).contains(&WindowState::Modal)
+            || self.conn.window_types(win).iter().any(|kind| {
+                matches!(
+                    kind,
+                    WindowType::Dialog
+                        | WindowType::Utility
+                        | WindowType::Toolbar
+                        | WindowType::Splash
+                        | WindowType::Menu
+                )
+            })
+    }
+}
@@ -23,45 +23,286 @@
  * SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
  */
 
+use crate::core::client::ClientManager;
 use crate::core::keys;
+use crate::core::layout::{de_pair, Direction, LayoutMessage};
+use serde::Deserialize;
 use std::collections::HashMap;
 use std::process::Command;
 
-type Callback = fn();
+// Everything a binding can ask the wm to do. The config file names them
+// in snake case, e.g. `{ set_layout = "tile" }` or `"kill"`.
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    Exec(Vec<String>),
+    Kill,
+    FocusNext,
+    FocusPrev,
+    Focus(Direction),
+    SwapNext,
+    SwapPrev,
+    Swap(Direction),
+    Workspace(usize),
+    NextWorkspace,
+    PrevWorkspace,
+    MoveToWorkspace(usize),
+    SetLayout(String),
+    NextLayout,
+    PrevLayout,
+    IncMsize(i16),
+    IncSsize(i16),
+    IncNmaster(i16),
+    // Outer and inner.
+    #[serde(deserialize_with = "de_pair")]
+    IncGaps(i16, i16),
+    ResetGaps,
+    Layout(LayoutMessage),
+    ToggleFloat,
+    // Switch to a named keymap until Escape, ExitMode or the timeout.
+    Mode(String),
+    // Switch to a named keymap for a single key.
+    Chord(String),
+    ExitMode,
+    Quit,
+    Restart,
+    // Read the config file again, also done on SIGHUP.
+    Reload,
+}
 
 pub struct Commands {
-    keybinds: HashMap<keys::KeyCombo, (Callback, Vec<String>)>,
+    keybinds: keys::KeyHandlers<Action>,
+    // Named keymaps, used instead of `keybinds` while active.
+    modes: HashMap<String, keys::KeyHandlers<Action>>,
+    mode: Option<ActiveMode>,
+}
+
+struct ActiveMode {
+    name: String,
+    // Chords are left after a single key.
+    oneshot: bool,
+}
+
+impl Action {
+    // Quit, Restart and Reload belong to the event loop, modes to `Commands`.
+    // Both are left to their owners.
+    pub fn run(&self, wm: &mut ClientManager) {
+        match self {
+            Action::Exec(cmd) => Action::exec(cmd),
+            Action::Kill => wm.kill(),
+            Action::FocusNext => wm.focus_next(),
+            Action::FocusPrev => wm.focus_prev(),
+            Action::Focus(dir) => wm.focus_direction(*dir),
+            Action::SwapNext => wm.swap_next(),
+            Action::SwapPrev => wm.swap_prev(),
+            Action::Swap(dir) => wm.swap_direction(*dir),
+            Action::Workspace(idx) => wm.switch_workspace(*idx),
+            Action::NextWorkspace => wm.next_workspace(),
+            Action::PrevWorkspace => wm.prev_workspace(),
+            Action::MoveToWorkspace(idx) => wm.move_to_workspace(*idx),
+            Action::SetLayout(name) => wm.set_layout(name),
+            Action::NextLayout => wm.next_layout(),
+            Action::PrevLayout => wm.prev_layout(),
+            Action::IncMsize(size) => wm.inc_msize(*size),
+            Action::IncSsize(size) => wm.inc_ssize(*size),
+            Action::IncNmaster(count) => wm.inc_nmaster(*count),
+            Action::IncGaps(outer, inner) => wm.inc_gaps(*outer, *inner),
+            Action::ResetGaps => wm.reset_gaps(),
+            Action::Layout(msg) => wm.layout_message(msg),
+            Action::ToggleFloat => wm.toggle_float(),
+            Action::Mode(_) | Action::Chord(_) | Action::ExitMode => {}
+            Action::Quit | Action::Restart | Action::Reload => {}
+        }
+    }
+
+    // Private
+    fn exec(cmd_str: &[String]) {
+        let (prog, args) = match cmd_str.split_first() {
+            Some(cmd) => cmd,
+            None => return,
+        };
+
+        if let Err(err) = Command::new(prog).args(args).spawn() {
+            println!("[E] Could not start {}: {}", prog, err);
+        }
+    }
 }
 
 impl Commands {
     pub fn new() -> Commands {
         Commands {
-            keybinds: HashMap::new(),
+            keybinds: keys::KeyHandlers::new(),
+            modes: HashMap::new(),
+            mode: None,
         }
     }
 
-    pub fn add(self, modkey: &[keys::ModKey], keysym: &str, func: Callback, args: Vec<String>) {
-        self.keybinds.insert(
-            keys::KeyCombo {
-                modmask: modkey[0].mask(),
-                keysym,
-            },
-            (func, args),
-        );
+    // Bind into the global keymap, or the one of `mode`.
+    pub fn bind(&mut self, mode: Option<&str>, keycombo: keys::KeyCombo, action: Action) {
+        match mode {
+            Some(mode) => self
+                .modes
+                .entry(mode.to_string())
+                .or_insert_with(keys::KeyHandlers::new)
+                .insert(keycombo, action),
+            None => self.keybinds.insert(keycombo, action),
+        }
     }
 
-    pub fn lookup(self, keycombo: &keys::KeyCombo) {
-        let func = self.keybinds.get(keycombo);
-        func;
+    pub fn lookup(&self, keycombo: &keys::KeyCombo) -> Option<&Action> {
+        match &self.mode {
+            Some(mode) => self
+                .modes
+                .get(&mode.name)
+                .and_then(|keybinds| keybinds.get(keycombo)),
+            None => self.keybinds.get(keycombo),
+        }
+    }
+
+    // Look the combo up in the active keymap and follow mode changes.
+    // Returns what is left for the caller to run.
+    pub fn dispatch(&mut self, keycombo: &keys::KeyCombo) -> Option<Action> {
+        let action = self.lookup(keycombo).cloned();
+
+        if let Some(mode) = &self.mode {
+            if action.is_none() && (keycombo.release || keycombo.is_modifier()) {
+                return None;
+            }
+            if mode.oneshot || (action.is_none() && keycombo.is_escape()) {
+                self.leave_mode();
+            }
+        }
+
+        match action {
+            Some(Action::Mode(name)) => self.enter_mode(&name, false),
+            Some(Action::Chord(name)) => self.enter_mode(&name, true),
+            Some(Action::ExitMode) => self.leave_mode(),
+            action => return action,
+        }
+        None
+    }
+
+    pub fn mode(&self) -> Option<&str> {
+        self.mode.as_ref().map(|mode| mode.name.as_str())
     }
 
-    pub fn exec(self, cmd_str: Vec<String>) {
-        let mut cmd = Command::new(&cmd_str[0]);
+    pub fn leave_mode(&mut self) {
+        self.mode = None;
+    }
+
+    pub fn key_handlers(&self) -> &keys::KeyHandlers<Action> {
+        &self.keybinds
+    }
 
-        for elem in cmd_str.iter().skip(1) {
-            cmd.arg(elem);
+    // Private
+    fn enter_mode(&mut self, name: &str, oneshot: bool) {
+        if self.modes.contains_key(name) {
+            self.mode = Some(ActiveMode {
+                name: name.to_string(),
+                oneshot,
+            });
+        } else {
+            println!("[E] Unknown mode {}.", name);
         }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn combo(text: &str) -> keys::KeyCombo {
+        keys::KeyCombo::parse(text, keys::ModKey::Mod4).unwrap()
+    }
+
+    // `$mod+r` enters "resize", `$mod+space` the chord "launch".
+    fn commands() -> Commands {
+        let mut ctrl = Commands::new();
+        ctrl.bind(None, combo("$mod+q"), Action::Kill);
+        ctrl.bind(None, combo("$mod+r"), Action::Mode("resize".to_string()));
+        ctrl.bind(
+            None,
+            combo("$mod+space"),
+            Action::Chord("launch".to_string()),
+        );
+        ctrl.bind(Some("resize"), combo("h"), Action::IncMsize(-5));
+        ctrl.bind(Some("resize"), combo("Return"), Action::ExitMode);
+        ctrl.bind(
+            Some("launch"),
+            combo("t"),
+            Action::Exec(vec!["xterm".to_string()]),
+        );
+        ctrl
+    }
+
+    #[test]
+    fn global_bindings_outside_modes() {
+        let mut ctrl = commands();
+        assert_eq!(ctrl.dispatch(&combo("$mod+q")), Some(Action::Kill));
+        assert_eq!(ctrl.dispatch(&combo("h")), None);
+        assert_eq!(ctrl.mode(), None);
+    }
+
+    #[test]
+    fn modes_stay_until_left() {
+        let mut ctrl = commands();
+        assert_eq!(ctrl.dispatch(&combo("$mod+r")), None);
+        assert_eq!(ctrl.mode(), Some("resize"));
+
+        assert_eq!(ctrl.dispatch(&combo("h")), Some(Action::IncMsize(-5)));
+        assert_eq!(ctrl.dispatch(&combo("h")), Some(Action::IncMsize(-5)));
+        // Global bindings are not active meanwhile.
+        assert_eq!(ctrl.dispatch(&combo("$mod+q")), None);
+        assert_eq!(ctrl.mode(), Some("resize"));
+
+        assert_eq!(ctrl.dispatch(&combo("Return")), None);
+        assert_eq!(ctrl.mode(), None);
+    }
+
+    #[test]
+    fn escape_leaves_any_mode() {
+        let mut ctrl = commands();
+        ctrl.dispatch(&combo("$mod+r"));
+        assert_eq!(ctrl.dispatch(&combo("Escape")), None);
+        assert_eq!(ctrl.mode(), None);
+
+        ctrl.dispatch(&combo("$mod+space"));
+        assert_eq!(ctrl.dispatch(&combo("Escape")), None);
+        assert_eq!(ctrl.mode(), None);
+    }
+
+    #[test]
+    fn chords_take_one_key() {
+        let mut ctrl = commands();
+        ctrl.dispatch(&combo("$mod+space"));
+        assert_eq!(ctrl.mode(), Some("launch"));
+        assert_eq!(
+            ctrl.dispatch(&combo("t")),
+            Some(Action::Exec(vec!["xterm".to_string()]))
+        );
+        assert_eq!(ctrl.mode(), None);
+
+        // An unbound key ends the chord as well.
+        ctrl.dispatch(&combo("$mod+space"));
+        assert_eq!(ctrl.dispatch(&combo("x")), None);
+        assert_eq!(ctrl.mode(), None);
+    }
+
+    #[test]
+    fn releases_and_modifiers_do_not_end_chords() {
+        let mut ctrl = commands();
+        ctrl.dispatch(&combo("$mod+space"));
+        assert_eq!(ctrl.dispatch(&combo("@space")), None);
+        assert_eq!(ctrl.dispatch(&combo("Super_L")), None);
+        assert_eq!(ctrl.dispatch(&combo("Shift+Shift_L")), None);
+        assert_eq!(ctrl.mode(), Some("launch"));
+    }
 
-        cmd.spawn().expect("Command failed to start.");
+    #[test]
+    fn unknown_modes_are_not_entered() {
+        let mut ctrl = commands();
+        ctrl.bind(None, combo("$mod+x"), Action::Mode("nosuch".to_string()));
+        assert_eq!(ctrl.dispatch(&combo("$mod+x")), None);
+        assert_eq!(ctrl.mode(), None);
     }
 }
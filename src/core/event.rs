@@ -23,32 +23,78 @@
  * SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
  */
 // crate
-use crate::core::keys::KeyCombo;
+use crate::core::keys::{KeyCombo, ModKey, ModMask};
+use crate::core::signal;
 use crate::core::x::Connection;
-use crate::core::x::Result;
 use crate::core::x::Window;
 
+// std
+use std::collections::VecDeque;
+use std::os::unix::io::AsRawFd;
+use std::time::{Duration, Instant};
+
 // thirdparty
 use xcb_util::keysyms::KeySymbols;
 
+// Consts
+
+// Set in the response type of events sent with SendEvent.
+const SYNTHETIC: u8 = 0x80;
+
 // Structs
 pub struct EventLoop<'a> {
     conn: &'a Connection,
+    // When to give up waiting and report a timeout.
+    deadline: Option<Instant>,
+    // Read ahead by `discard_enter_events`, handed out first.
+    queued: VecDeque<xcb::GenericEvent>,
 }
 
 pub enum Event {
     MapRequest(Window),
     UnmapNotify(Window),
+    // A client withdrew a window we had unmapped ourselves.
+    Withdrawn(Window),
     DestroyNotify(Window),
     KeyPress(KeyCombo),
+    KeyRelease(KeyCombo),
     EnterNotify(Window),
+    PropertyNotify(Window),
+    // The keyboard mapping changed, key grabs are stale.
+    MappingNotify,
+    // Nothing happened before the timeout ran out.
+    Timeout,
+    // SIGHUP, asking for the config to be read again.
+    Hangup,
 }
 
 // Impl
 
 impl<'a> EventLoop<'a> {
-    pub fn new(conn: &'a Connection) -> Result<EventLoop<'a>> {
-        Ok(EventLoop { conn })
+    pub fn new(conn: &'a Connection) -> EventLoop<'a> {
+        EventLoop {
+            conn,
+            deadline: None,
+            queued: VecDeque::new(),
+        }
+    }
+
+    // Windows moved under the pointer by a key action or a re-render
+    // cause EnterNotify events, which would take the focus along. Drop
+    // them like dwm does, everything else is kept.
+    pub fn discard_enter_events(&mut self) {
+        self.conn.sync();
+        while let Some(event) = self.conn.conn.poll_for_queued_event() {
+            if event.response_type() & !SYNTHETIC != xcb::ENTER_NOTIFY {
+                self.queued.push_back(event);
+            }
+        }
+    }
+
+    // Emit a single `Event::Timeout` if nothing arrives in time, `None`
+    // waits forever.
+    pub fn set_timeout(&mut self, timeout: Option<Duration>) {
+        self.deadline = timeout.map(|timeout| Instant::now() + timeout);
     }
 
     pub fn on_configure_request(&self, event: &xcb::ConfigureRequestEvent) -> Option<Event> {
@@ -61,7 +107,7 @@ impl<'a> EventLoop<'a> {
                 xcb::CONFIG_WINDOW_BORDER_WIDTH as u16,
                 u32::from(event.border_width()),
             ),
-            (xcb::CONFIG_WINDOW_SIBLING as u16, event.sibling() as u32),
+            (xcb::CONFIG_WINDOW_SIBLING as u16, event.sibling()),
             (
                 xcb::CONFIG_WINDOW_STACK_MODE as u16,
                 u32::from(event.stack_mode()),
@@ -84,17 +130,43 @@ impl<'a> EventLoop<'a> {
     pub fn on_key_press(&self, event: &xcb::KeyPressEvent) -> Option<Event> {
         let symbols = KeySymbols::new(&self.conn.conn);
         let keysym = symbols.press_lookup_keysym(event, 0);
-        let modmask = u32::from(event.state());
-        let key = KeyCombo { modmask, keysym };
+        let modmask = self.clean_mask(event.state());
+        let key = KeyCombo {
+            modmask,
+            keysym,
+            release: false,
+        };
         Some(Event::KeyPress(key))
     }
 
+    // The state still holds a modifier while it is released, so it is
+    // taken out to match a binding on the bare modifier key.
+    pub fn on_key_release(&self, event: &xcb::KeyReleaseEvent) -> Option<Event> {
+        let symbols = KeySymbols::new(&self.conn.conn);
+        let keysym = symbols.release_lookup_keysym(event, 0);
+        let mut key = KeyCombo {
+            modmask: self.clean_mask(event.state()),
+            keysym,
+            release: true,
+        };
+
+        if key.is_modifier() {
+            key.modmask &= !self.conn.modifier_mask(keysym);
+        }
+        Some(Event::KeyRelease(key))
+    }
+
     pub fn on_map_request(&self, event: &xcb::MapRequestEvent) -> Option<Event> {
         Some(Event::MapRequest(Window(event.window())))
     }
 
+    // Real unmaps show up twice, on the window and on the root. A client
+    // withdrawing an already unmapped window only sends a synthetic one to
+    // the root, as the ICCCM asks.
     pub fn on_unmap_notify(&self, event: &xcb::UnmapNotifyEvent) -> Option<Event> {
-        if event.event() != self.conn.window_root().get() {
+        if event.response_type() & SYNTHETIC != 0 {
+            Some(Event::Withdrawn(Window(event.window())))
+        } else if event.event() != self.conn.window_root().get() {
             Some(Event::UnmapNotify(Window(event.window())))
         } else {
             None
@@ -104,6 +176,51 @@ impl<'a> EventLoop<'a> {
     pub fn on_enter_notify(&self, event: &xcb::EnterNotifyEvent) -> Option<Event> {
         Some(Event::EnterNotify(Window(event.event())))
     }
+
+    pub fn on_mapping_notify(&self, event: &xcb::MappingNotifyEvent) -> Option<Event> {
+        let request = u32::from(event.request());
+        if request == xcb::MAPPING_KEYBOARD || request == xcb::MAPPING_MODIFIER {
+            Some(Event::MappingNotify)
+        } else {
+            None
+        }
+    }
+
+    // Only the hints are of interest, they carry the urgency flag.
+    pub fn on_property_notify(&self, event: &xcb::PropertyNotifyEvent) -> Option<Event> {
+        if event.atom() == xcb::ATOM_WM_HINTS {
+            Some(Event::PropertyNotify(Window(event.window())))
+        } else {
+            None
+        }
+    }
+
+    // Private
+    fn clean_mask(&self, state: u16) -> ModMask {
+        u32::from(state) & ModKey::mask_all() & !self.conn.lock_mask()
+    }
+
+    // Block until X or a signal has something for us, or `timeout` ran
+    // out, which is the only case returning false.
+    fn wait(&self, timeout: Option<Duration>) -> bool {
+        let mut fds = [
+            libc::pollfd {
+                fd: self.conn.conn.as_raw_fd(),
+                events: libc::POLLIN,
+                revents: 0,
+            },
+            libc::pollfd {
+                fd: signal::hangup_fd(),
+                events: libc::POLLIN,
+                revents: 0,
+            },
+        ];
+        let millis = timeout.map_or(-1, |timeout| {
+            timeout.as_millis().min(libc::c_int::MAX as u128) as libc::c_int
+        });
+
+        unsafe { libc::poll(fds.as_mut_ptr(), fds.len() as libc::nfds_t, millis) != 0 }
+    }
 }
 
 impl<'a> Iterator for EventLoop<'a> {
@@ -113,20 +230,44 @@ impl<'a> Iterator for EventLoop<'a> {
         loop {
             self.conn.flush();
 
-            let event = self
-                .conn
-                .conn
-                .wait_for_event()
-                .expect("[E] IO error while waiting for event.");
+            if signal::take_hangup() {
+                return Some(Event::Hangup);
+            }
+
+            let event = match self
+                .queued
+                .pop_front()
+                .or_else(|| self.conn.conn.poll_for_event())
+            {
+                Some(event) => event,
+                None => {
+                    self.conn
+                        .conn
+                        .has_error()
+                        .expect("[E] IO error while waiting for event.");
+
+                    let timeout = self
+                        .deadline
+                        .map(|deadline| deadline.saturating_duration_since(Instant::now()));
+                    if !self.wait(timeout) {
+                        self.deadline = None;
+                        return Some(Event::Timeout);
+                    }
+                    continue;
+                }
+            };
 
             unsafe {
-                let propagate = match event.response_type() {
+                let propagate = match event.response_type() & !SYNTHETIC {
                     xcb::CONFIGURE_REQUEST => self.on_configure_request(xcb::cast_event(&event)),
                     xcb::MAP_REQUEST => self.on_map_request(xcb::cast_event(&event)),
                     xcb::UNMAP_NOTIFY => self.on_unmap_notify(xcb::cast_event(&event)),
                     xcb::DESTROY_NOTIFY => self.on_destroy_notify(xcb::cast_event(&event)),
                     xcb::KEY_PRESS => self.on_key_press(xcb::cast_event(&event)),
+                    xcb::KEY_RELEASE => self.on_key_release(xcb::cast_event(&event)),
                     xcb::ENTER_NOTIFY => self.on_enter_notify(xcb::cast_event(&event)),
+                    xcb::PROPERTY_NOTIFY => self.on_property_notify(xcb::cast_event(&event)),
+                    xcb::MAPPING_NOTIFY => self.on_mapping_notify(xcb::cast_event(&event)),
                     _ => None,
                 };
 
@@ -24,7 +24,11 @@
  */
 
 use std::collections::HashMap;
+use std::ffi::{CStr, CString};
+use std::fmt;
 use std::os::raw::c_uint;
+use x11::{keysym, xlib};
+use xcb_util::keysyms;
 
 pub type ModMask = c_uint;
 pub type Key = c_uint;
@@ -54,6 +58,34 @@ impl ModKey {
             | xcb::MOD_MASK_5
     }
 
+    // Case does not matter, Ctrl, Alt and Super work too.
+    pub fn from_name(name: &str) -> Option<ModKey> {
+        match name.to_lowercase().as_str() {
+            "shift" => Some(ModKey::Shift),
+            "lock" => Some(ModKey::Lock),
+            "control" | "ctrl" => Some(ModKey::Control),
+            "mod1" | "alt" => Some(ModKey::Mod1),
+            "mod2" => Some(ModKey::Mod2),
+            "mod3" => Some(ModKey::Mod3),
+            "mod4" | "super" => Some(ModKey::Mod4),
+            "mod5" => Some(ModKey::Mod5),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ModKey::Shift => "Shift",
+            ModKey::Lock => "Lock",
+            ModKey::Control => "Control",
+            ModKey::Mod1 => "Mod1",
+            ModKey::Mod2 => "Mod2",
+            ModKey::Mod3 => "Mod3",
+            ModKey::Mod4 => "Mod4",
+            ModKey::Mod5 => "Mod5",
+        }
+    }
+
     pub fn mask(self) -> ModMask {
         match self {
             ModKey::Shift => xcb::MOD_MASK_SHIFT,
@@ -72,24 +104,114 @@ impl ModKey {
 pub struct KeyCombo {
     pub modmask: ModMask,
     pub keysym: Key,
+    // Fires when the key goes up instead of down.
+    pub release: bool,
 }
 
 impl KeyCombo {
-    fn new(mods: &[ModKey], keysym_: Key) -> KeyCombo {
+    pub fn new(mods: &[ModKey], keysym_: Key) -> KeyCombo {
         let modmask_ = mods.iter().fold(0, |mask, mod_key| mask | mod_key.mask());
         KeyCombo {
             modmask: modmask_,
             keysym: keysym_,
+            release: false,
+        }
+    }
+
+    // Parse a keycombo like "Mod4+Shift+Return" or
+    // "Super+ctrl+XF86AudioRaiseVolume". "$mod" stands for `modkey`, an
+    // "@" in front of the key binds its release and "++" ends in the plus
+    // key.
+    pub fn parse(text: &str, modkey: ModKey) -> Result<KeyCombo, String> {
+        let trimmed = text.trim();
+        let (mods, key) = match trimmed.strip_suffix("++") {
+            Some(mods) => (mods, "+"),
+            None if trimmed == "+" => ("", "+"),
+            None => match trimmed.rfind('+') {
+                Some(pos) => (&trimmed[..pos], trimmed[pos + 1..].trim()),
+                None => ("", trimmed),
+            },
+        };
+        let (key, release) = match key.strip_prefix('@') {
+            Some(key) => (key.trim(), true),
+            None => (key, false),
+        };
+        if key.is_empty() {
+            return Err(format!("missing key in `{}`", text));
+        }
+
+        let mods = match mods.trim() {
+            "" => Vec::new(),
+            mods => mods
+                .split('+')
+                .map(|name| match name.trim() {
+                    "$mod" => Ok(modkey),
+                    name => ModKey::from_name(name)
+                        .ok_or_else(|| format!("unknown modifier `{}` in `{}`", name, text)),
+                })
+                .collect::<Result<Vec<ModKey>, String>>()?,
+        };
+        let keysym =
+            keysym_from_name(key).ok_or_else(|| format!("unknown key `{}` in `{}`", key, text))?;
+
+        let keycombo = KeyCombo::new(&mods, keysym);
+        Ok(if release {
+            keycombo.on_release()
+        } else {
+            keycombo
+        })
+    }
+
+    pub fn on_release(self) -> KeyCombo {
+        KeyCombo {
+            release: true,
+            ..self
         }
     }
+
+    // Shift, Super and friends on their own, they only ever start a combo.
+    pub fn is_modifier(&self) -> bool {
+        keysyms::is_modifier_key(self.keysym)
+    }
+
+    pub fn is_escape(&self) -> bool {
+        self.modmask == 0 && self.keysym == keysym::XK_Escape && !self.release
+    }
 }
 
-pub struct KeyHandlers {
-    hashmap: HashMap<KeyCombo, ()>,
+// The other way around, as `parse` reads it.
+impl fmt::Display for KeyCombo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let order = [
+            ModKey::Mod1,
+            ModKey::Mod2,
+            ModKey::Mod3,
+            ModKey::Mod4,
+            ModKey::Mod5,
+            ModKey::Control,
+            ModKey::Lock,
+            ModKey::Shift,
+        ];
+
+        for modkey in order
+            .iter()
+            .filter(|modkey| self.modmask & modkey.mask() != 0)
+        {
+            write!(f, "{}+", modkey.name())?;
+        }
+        if self.release {
+            write!(f, "@")?;
+        }
+        write!(f, "{}", keysym_name(self.keysym))
+    }
+}
+
+pub struct KeyHandlers<T> {
+    hashmap: HashMap<KeyCombo, T>,
 }
 
-impl KeyHandlers {
-    pub fn new() -> KeyHandlers {
+impl<T> KeyHandlers<T> {
+    pub fn new() -> KeyHandlers<T> {
         KeyHandlers {
             hashmap: HashMap::new(),
         }
@@ -99,13 +221,17 @@ impl KeyHandlers {
         self.hashmap.keys().collect()
     }
 
-    pub fn get(&self, key_combo: &KeyCombo) -> Option<()> {
-        self.hashmap.get(key_combo).cloned()
+    pub fn get(&self, key_combo: &KeyCombo) -> Option<&T> {
+        self.hashmap.get(key_combo)
+    }
+
+    pub fn insert(&mut self, key_combo: KeyCombo, handler: T) {
+        self.hashmap.insert(key_combo, handler);
     }
 }
 
-impl From<Vec<(Vec<ModKey>, Key, ())>> for KeyHandlers {
-    fn from(handlers: Vec<(Vec<ModKey>, Key, ())>) -> KeyHandlers {
+impl<T> From<Vec<(Vec<ModKey>, Key, T)>> for KeyHandlers<T> {
+    fn from(handlers: Vec<(Vec<ModKey>, Key, T)>) -> KeyHandlers<T> {
         let mut hashmap = HashMap::new();
         for (modkeys, keysym, handler) in handlers {
             hashmap.insert(KeyCombo::new(&modkeys, keysym), handler);
@@ -113,3 +239,152 @@ impl From<Vec<(Vec<ModKey>, Key, ())>> for KeyHandlers {
         KeyHandlers { hashmap }
     }
 }
+
+// Resolve a keysym name like "Return" or "p". Xlib knows the names,
+// a few friendlier spellings, in any case, are mapped first. Letters are
+// always lowercase, a key press reports the unshifted keysym.
+pub fn keysym_from_name(name: &str) -> Option<Key> {
+    let lower = name.to_lowercase();
+    let name = match lower.as_str() {
+        "enter" | "return" => "Return",
+        "esc" | "escape" => "Escape",
+        "del" | "delete" => "Delete",
+        "backspace" => "BackSpace",
+        "tab" => "Tab",
+        "space" => "space",
+        "left" => "Left",
+        "right" => "Right",
+        "up" => "Up",
+        "down" => "Down",
+        "+" => "plus",
+        letter if letter.chars().count() == 1 => letter,
+        _ => name,
+    };
+    let cname = CString::new(name).ok()?;
+
+    match unsafe { xlib::XStringToKeysym(cname.as_ptr()) } {
+        0 => keysym_from_name_nocase(name),
+        keysym => Some(keysym as Key),
+    }
+}
+
+// Xlib only knows the exact spelling, so compare against the names of
+// the 16 bit keysyms and the XF86 media keys instead.
+fn keysym_from_name_nocase(name: &str) -> Option<Key> {
+    (0..=0xffff)
+        .chain(0x1008_ff00..=0x1008_ffff)
+        .find(|keysym| {
+            let known = unsafe { xlib::XKeysymToString(*keysym) };
+            !known.is_null()
+                && unsafe { CStr::from_ptr(known) }
+                    .to_bytes()
+                    .eq_ignore_ascii_case(name.as_bytes())
+        })
+        .map(|keysym| keysym as Key)
+}
+
+// The name Xlib uses for a keysym, its number if there is none.
+pub fn keysym_name(keysym: Key) -> String {
+    let name = unsafe { xlib::XKeysymToString(xlib::KeySym::from(keysym)) };
+
+    if name.is_null() {
+        format!("{:#x}", keysym)
+    } else {
+        unsafe { CStr::from_ptr(name) }
+            .to_string_lossy()
+            .into_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> Result<KeyCombo, String> {
+        KeyCombo::parse(text, ModKey::Mod4)
+    }
+
+    fn roundtrip(text: &str) -> String {
+        let keycombo = parse(text).unwrap();
+        let formatted = keycombo.to_string();
+        assert_eq!(parse(&formatted), Ok(keycombo), "{}", formatted);
+        formatted
+    }
+
+    #[test]
+    fn parse_mod_variable() {
+        assert_eq!(
+            parse("$mod+Shift+Return"),
+            Ok(KeyCombo::new(
+                &[ModKey::Mod4, ModKey::Shift],
+                keysym::XK_Return
+            ))
+        );
+        assert_eq!(
+            KeyCombo::parse("$mod+x", ModKey::Mod1),
+            Ok(KeyCombo::new(&[ModKey::Mod1], keysym::XK_x))
+        );
+    }
+
+    #[test]
+    fn parse_aliases() {
+        assert_eq!(parse("super+CTRL+enter"), parse("Mod4+Control+Return"));
+        assert_eq!(parse("Alt+esc"), parse("Mod1+Escape"));
+        assert_eq!(parse(" Shift + Tab "), parse("Shift+Tab"));
+    }
+
+    #[test]
+    fn parse_names_ignore_case() {
+        assert_eq!(parse("$mod+f5"), parse("$mod+F5"));
+        assert_eq!(parse("print"), parse("Print"));
+        assert_eq!(parse("xf86audioraisevolume"), parse("XF86AudioRaiseVolume"));
+        assert!(parse("XF86AudioRaiseVolume").is_ok());
+        assert!(parse("nosuchkey").is_err());
+    }
+
+    #[test]
+    fn parse_letters_unshifted() {
+        assert_eq!(parse("$mod+Shift+D"), parse("$mod+Shift+d"));
+        assert_eq!(parse("D").map(|key| key.keysym), Ok(keysym::XK_d));
+    }
+
+    #[test]
+    fn parse_plus_key() {
+        let plus = Ok(KeyCombo::new(&[ModKey::Mod4], keysym::XK_plus));
+        assert_eq!(parse("$mod++"), plus);
+        assert_eq!(parse("$mod+plus"), plus);
+        assert_eq!(parse("+").map(|key| key.keysym), Ok(keysym::XK_plus));
+    }
+
+    #[test]
+    fn parse_release() {
+        let keycombo = parse("@Print").unwrap();
+        assert!(keycombo.release);
+        assert_eq!(keycombo.keysym, keysym::XK_Print);
+        assert!(parse("$mod+@x").unwrap().release);
+        assert!(!parse("$mod+x").unwrap().release);
+    }
+
+    #[test]
+    fn parse_bad_input() {
+        assert!(parse("").is_err());
+        assert!(parse("$mod+").is_err());
+        assert!(parse("@").is_err());
+        assert!(parse("Hyper+x").is_err());
+        assert!(parse("$mod+nosuchkey").is_err());
+        assert!(parse("Mod4++x").is_err());
+    }
+
+    #[test]
+    fn format_roundtrip() {
+        assert_eq!(roundtrip("$mod+Shift+Return"), "Mod4+Shift+Return");
+        assert_eq!(
+            roundtrip("shift+super+ctrl+alt+k"),
+            "Mod1+Mod4+Control+Shift+k"
+        );
+        assert_eq!(roundtrip("XF86AudioRaiseVolume"), "XF86AudioRaiseVolume");
+        assert_eq!(roundtrip("$mod++"), "Mod4+plus");
+        assert_eq!(roundtrip("@Print"), "@Print");
+        assert_eq!(roundtrip("$mod+@enter"), "Mod4+@Return");
+    }
+}
@@ -23,11 +23,331 @@
  * SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
  */
 
-use crate::core::x::Connection;
+use crate::core::x::{Connection, Window};
+use serde::{Deserialize, Deserializer};
 
-pub trait Layout<'a> {
-    fn new(space: u16, conn: &'a Connection) -> Self;
-    fn inc_msize(&mut self, size: u16);
-    fn inc_ssize(&mut self, size: u16);
-    fn render(&self);
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
+// Requests only some layouts know how to handle.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum LayoutMessage {
+    #[serde(deserialize_with = "de_pair")]
+    Preselect(Direction, u16),
+    CancelPreselect,
+    Rotate,
+    FlipHorizontal,
+    FlipVertical,
+    #[serde(deserialize_with = "de_pair")]
+    ResizeSplit(Direction, i16),
+}
+
+// Lets two-field variants be written as a plain array, like
+// `{ preselect = ["left", 50] }`.
+pub fn de_pair<'de, D, A, B>(deserializer: D) -> Result<(A, B), D::Error>
+where
+    D: Deserializer<'de>,
+    A: Deserialize<'de>,
+    B: Deserialize<'de>,
+{
+    <(A, B)>::deserialize(deserializer)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Geometry {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Geometry {
+    pub fn new(x: i32, y: i32, width: u32, height: u32) -> Geometry {
+        Geometry {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    // Split into `count` columns of equal width.
+    pub fn columns(&self, count: usize) -> Vec<Geometry> {
+        Geometry::split(self.width, count)
+            .into_iter()
+            .map(|(offset, width)| Geometry::new(self.x + offset, self.y, width, self.height))
+            .collect()
+    }
+
+    // Split into `count` rows of equal height.
+    pub fn rows(&self, count: usize) -> Vec<Geometry> {
+        Geometry::split(self.height, count)
+            .into_iter()
+            .map(|(offset, height)| Geometry::new(self.x, self.y + offset, self.width, height))
+            .collect()
+    }
+
+    // Cut in two side by side. The left part gets `ratio` percent.
+    pub fn divide_columns(&self, ratio: u16) -> (Geometry, Geometry) {
+        let left = self.width * u32::from(ratio) / 100;
+
+        (
+            Geometry::new(self.x, self.y, left, self.height),
+            Geometry::new(self.x + left as i32, self.y, self.width - left, self.height),
+        )
+    }
+
+    // Cut in two on top of each other. The top part gets `ratio` percent.
+    pub fn divide_rows(&self, ratio: u16) -> (Geometry, Geometry) {
+        let top = self.height * u32::from(ratio) / 100;
+
+        (
+            Geometry::new(self.x, self.y, self.width, top),
+            Geometry::new(self.x, self.y + top as i32, self.width, self.height - top),
+        )
+    }
+
+    // The size X wants for a window drawn with a border around it.
+    pub fn without_border(&self, border: u32) -> Geometry {
+        Geometry::new(
+            self.x,
+            self.y,
+            self.width.saturating_sub(2 * border).max(1),
+            self.height.saturating_sub(2 * border).max(1),
+        )
+    }
+
+    // Move every edge `amount` pixels inwards.
+    pub fn shrink(&self, amount: u32) -> Geometry {
+        Geometry::new(
+            self.x + amount as i32,
+            self.y + amount as i32,
+            self.width.saturating_sub(2 * amount),
+            self.height.saturating_sub(2 * amount),
+        )
+    }
+
+    pub fn center(&self) -> (i32, i32) {
+        (
+            self.x + (self.width / 2) as i32,
+            self.y + (self.height / 2) as i32,
+        )
+    }
+
+    // Index of the closest of `others` whose center lies in direction
+    // `dir` of ours.
+    pub fn nearest(&self, dir: Direction, others: &[Geometry]) -> Option<usize> {
+        let (x, y) = self.center();
+
+        others
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, geo)| {
+                let (dx, dy) = (geo.center().0 - x, geo.center().1 - y);
+                let ahead = match dir {
+                    Direction::Left => dx < 0,
+                    Direction::Right => dx > 0,
+                    Direction::Up => dy < 0,
+                    Direction::Down => dy > 0,
+                };
+
+                if ahead {
+                    Some((idx, i64::from(dx).pow(2) + i64::from(dy).pow(2)))
+                } else {
+                    None
+                }
+            })
+            .min_by_key(|(_, dist)| *dist)
+            .map(|(idx, _)| idx)
+    }
+
+    // Offsets and sizes of `count` equal parts of `total`. The last part
+    // takes the rounding remainder.
+    fn split(total: u32, count: usize) -> Vec<(i32, u32)> {
+        if count == 0 {
+            return Vec::new();
+        }
+
+        let size = total / count as u32;
+
+        (0..count as u32)
+            .map(|i| {
+                let offset = (i * size) as i32;
+                if i == count as u32 - 1 {
+                    (offset, total - size * i)
+                } else {
+                    (offset, size)
+                }
+            })
+            .collect()
+    }
+}
+
+// Space at the screen edges and between tiled windows.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Gaps {
+    pub outer: u16,
+    pub inner: u16,
+    // Drop both while only one window is tiled.
+    pub smart: bool,
+}
+
+impl Gaps {
+    pub fn new(outer: u16, inner: u16, smart: bool) -> Gaps {
+        Gaps {
+            outer,
+            inner,
+            smart,
+        }
+    }
+
+    // The area handed to the layout. It overlaps the outer gap by half
+    // the inner gap, which `window` takes back from every window.
+    pub fn screen(&self, screen: &Geometry, count: usize) -> Geometry {
+        if self.smart && count == 1 {
+            return *screen;
+        }
+
+        let outer = i32::from(self.outer);
+        let inner = i32::from(self.inner);
+        let before = outer - inner / 2;
+        let after = outer - (inner - inner / 2);
+
+        Geometry::new(
+            screen.x + before,
+            screen.y + before,
+            (screen.width as i32 - before - after).max(1) as u32,
+            (screen.height as i32 - before - after).max(1) as u32,
+        )
+    }
+
+    pub fn window(&self, geo: &Geometry, count: usize) -> Geometry {
+        if self.smart && count == 1 {
+            return *geo;
+        }
+
+        let inner = u32::from(self.inner);
+        Geometry::new(
+            geo.x + (inner / 2) as i32,
+            geo.y + (inner / 2) as i32,
+            geo.width.saturating_sub(inner).max(1),
+            geo.height.saturating_sub(inner).max(1),
+        )
+    }
+}
+
+pub trait Layout {
+    fn name(&self) -> &str;
+    fn inc_msize(&mut self, size: i16);
+    fn inc_ssize(&mut self, size: i16);
+
+    // Layouts without a master area ignore this.
+    fn inc_nmaster(&mut self, _count: i16) {}
+
+    // Layouts keeping state per client, like a split tree, track the
+    // workspace through these. `focused` is the client focused before.
+    fn client_added(&mut self, _win: &Window, _focused: Option<&Window>) {}
+    fn client_removed(&mut self, _win: &Window) {}
+    fn client_swapped(&mut self, _a: &Window, _b: &Window) {}
+
+    // Returns whether the message was understood.
+    fn handle(&mut self, _msg: &LayoutMessage, _focused: Option<&Window>) -> bool {
+        false
+    }
+
+    // Whether tiled windows get a border when `count` of them are shown.
+    fn borders(&self, _count: usize) -> bool {
+        true
+    }
+
+    // Pure arrangement math. Returns one geometry per client, in order.
+    fn arrange(&self, screen: &Geometry, clients: &[Window]) -> Vec<Geometry>;
+
+    fn render(
+        &self,
+        conn: &Connection,
+        screen: &Geometry,
+        gaps: &Gaps,
+        border: u32,
+        clients: &[Window],
+    ) {
+        let count = clients.len();
+        let border = if self.borders(count) { border } else { 0 };
+        let geos = self.arrange(&gaps.screen(screen, count), clients);
+
+        for (win, geo) in clients.iter().zip(geos) {
+            conn.window_set_border_width(win, border);
+            conn.window_configure(win, &gaps.window(&geo, count).without_border(border));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layouts::SCREEN;
+
+    #[test]
+    fn split_gives_the_remainder_to_the_last_part() {
+        assert_eq!(
+            SCREEN.columns(3),
+            vec![
+                Geometry::new(0, 0, 333, 800),
+                Geometry::new(333, 0, 333, 800),
+                Geometry::new(666, 0, 334, 800),
+            ]
+        );
+        assert_eq!(SCREEN.rows(0), vec![]);
+    }
+
+    #[test]
+    fn gaps_between_windows_match_the_outer_ones() {
+        let gaps = Gaps::new(10, 6, false);
+        let screen = gaps.screen(&SCREEN, 2);
+        assert_eq!(screen, Geometry::new(7, 7, 986, 786));
+
+        let (left, right) = screen.divide_columns(50);
+        assert_eq!(gaps.window(&left, 2), Geometry::new(10, 10, 487, 780));
+        assert_eq!(gaps.window(&right, 2), Geometry::new(503, 10, 487, 780));
+    }
+
+    #[test]
+    fn odd_inner_gaps_keep_the_outer_gap() {
+        let gaps = Gaps::new(10, 5, false);
+        let screen = gaps.screen(&SCREEN, 2);
+        assert_eq!(screen, Geometry::new(8, 8, 985, 785));
+        assert_eq!(gaps.window(&screen, 2), Geometry::new(10, 10, 980, 780));
+    }
+
+    #[test]
+    fn smart_gaps_only_drop_for_a_single_window() {
+        let gaps = Gaps::new(10, 6, true);
+        assert_eq!(gaps.screen(&SCREEN, 1), SCREEN);
+        assert_eq!(gaps.window(&SCREEN, 1), SCREEN);
+        assert_eq!(gaps.screen(&SCREEN, 2), Geometry::new(7, 7, 986, 786));
+
+        let gaps = Gaps::new(10, 6, false);
+        assert_eq!(gaps.screen(&SCREEN, 1), Geometry::new(7, 7, 986, 786));
+        assert_eq!(gaps.window(&SCREEN, 1), Geometry::new(3, 3, 994, 794));
+    }
+
+    #[test]
+    fn nearest_looks_only_ahead() {
+        let others = [
+            Geometry::new(0, 0, 100, 100),
+            Geometry::new(200, 0, 100, 100),
+            Geometry::new(400, 0, 100, 100),
+        ];
+        let geo = Geometry::new(200, 0, 100, 100);
+        assert_eq!(geo.nearest(Direction::Left, &others), Some(0));
+        assert_eq!(geo.nearest(Direction::Right, &others), Some(2));
+        assert_eq!(geo.nearest(Direction::Up, &others), None);
+    }
 }
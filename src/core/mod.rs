@@ -24,9 +24,13 @@
  */
 
 // Public
+pub mod border;
+pub mod client;
 pub mod command;
 pub mod event;
 pub mod keys;
 pub mod layout;
+pub mod rule;
+pub mod signal;
 pub mod workspace;
 pub mod x;
@@ -0,0 +1,108 @@
+/*
+ * Copyright (c) 2020, Florian Büstgens
+ * All rights reserved.
+ *
+ * Redistribution and use in source and binary forms, with or without
+ * modification, are permitted provided that the following conditions are met:
+ *     1. Redistributions of source code must retain the above copyright
+ *        notice, this list of conditions and the following disclaimer.
+ *
+ *     2. Redistributions in binary form must reproduce the above copyright notice,
+ *        this list of conditions and the following disclaimer in the
+ *        documentation and/or other materials provided with the distribution.
+ *
+ * THIS SOFTWARE IS PROVIDED BY <copyright holder> ''AS IS'' AND ANY
+ * EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+ * WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+ * DISCLAIMED. IN NO EVENT SHALL <copyright holder> BE LIABLE FOR ANY
+ * DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+ * (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+ * LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND
+ * ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+ * (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+ * SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+ */
+// crate
+use crate::core::x::Result;
+
+// std
+use std::os::unix::io::RawFd;
+use std::sync::atomic::{AtomicI32, Ordering};
+
+// A SIGHUP becomes a byte on this pipe, so the event loop can wait for it
+// next to the X connection.
+static HANGUP_READ: AtomicI32 = AtomicI32::new(-1);
+static HANGUP_WRITE: AtomicI32 = AtomicI32::new(-1);
+
+pub fn catch_hangup() -> Result<()> {
+    let mut fds: [libc::c_int; 2] = [-1; 2];
+
+    unsafe {
+        if libc::pipe(fds.as_mut_ptr()) != 0 {
+            return Err(failure::format_err!("[E] Could not create the signal pipe"));
+        }
+        for fd in &fds {
+            libc::fcntl(*fd, libc::F_SETFL, libc::O_NONBLOCK);
+            libc::fcntl(*fd, libc::F_SETFD, libc::FD_CLOEXEC);
+        }
+
+        HANGUP_READ.store(fds[0], Ordering::SeqCst);
+        HANGUP_WRITE.store(fds[1], Ordering::SeqCst);
+        libc::signal(
+            libc::SIGHUP,
+            on_hangup as extern "C" fn(libc::c_int) as libc::sighandler_t,
+        );
+    }
+
+    Ok(())
+}
+
+// Programs started from bindings are never waited for. Reap them as they
+// exit, so they do not linger as zombies until void quits. The handler,
+// unlike SIG_IGN, is not inherited by what they exec in turn.
+pub fn reap_children() {
+    unsafe {
+        libc::signal(
+            libc::SIGCHLD,
+            on_child as extern "C" fn(libc::c_int) as libc::sighandler_t,
+        );
+    }
+
+    // Children that exited before, e.g. across a restart.
+    on_child(0);
+}
+
+// -1 until `catch_hangup` ran.
+pub fn hangup_fd() -> RawFd {
+    HANGUP_READ.load(Ordering::SeqCst)
+}
+
+// Whether a SIGHUP arrived since the last call.
+pub fn take_hangup() -> bool {
+    let fd = hangup_fd();
+    let mut buf = [0u8; 16];
+    let mut caught = false;
+
+    while fd >= 0 && unsafe { libc::read(fd, buf.as_mut_ptr() as *mut libc::c_void, buf.len()) } > 0
+    {
+        caught = true;
+    }
+
+    caught
+}
+
+// Private
+extern "C" fn on_hangup(_: libc::c_int) {
+    let byte = 0u8;
+    unsafe {
+        libc::write(
+            HANGUP_WRITE.load(Ordering::SeqCst),
+            &byte as *const u8 as *const libc::c_void,
+            1,
+        );
+    }
+}
+
+extern "C" fn on_child(_: libc::c_int) {
+    unsafe { while libc::waitpid(-1, std::ptr::null_mut(), libc::WNOHANG) > 0 {} }
+}
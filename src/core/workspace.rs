@@ -22,34 +22,496 @@
  * (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
  * SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
  */
+// crate
+use crate::core::layout::{Gaps, Geometry, Layout, LayoutMessage};
+use crate::core::x::{Connection, Window};
 
-// pub struct Workspace<'a, T: Layout<'a>> {
-//     name: &'a str,
-//     layout: T,
-// }
-
-// // Impl
-
-// impl<'a> Workspace<'a> {
-//     fn push_master(&self, win: &'a Window) {
-//         self.master.push(win);
-//     }
-
-//     fn pop_master(&self) -> &'a Window {
-//         self.master
-//             .first()
-//             .ok_or(|| format_err!("[!] No Windows on master"))
-//             .unwrap()
-//     }
-
-//     fn push_slave(&self, win: &'a Window) {
-//         self.slave.push(win);
-//     }
-
-//     fn pop_slave(&self) -> &'a Window {
-//         self.slave
-//             .first()
-//             .ok_or(|| format_err!("[!] No Windows on slave"))
-//             .unwrap()
-//     }
-// }
+// Structs
+pub struct Workspace {
+    name: String,
+    clients: Vec<Window>,
+    focused: Option<Window>,
+    // Clients kept out of the layout.
+    floating: Vec<Window>,
+    // Every layout keeps its own state, only one of them is shown.
+    layouts: Vec<Box<dyn Layout>>,
+    layout: usize,
+    gaps: Gaps,
+    // What `reset_gaps` goes back to.
+    default_gaps: Gaps,
+}
+
+pub struct WorkspaceSet {
+    workspaces: Vec<Workspace>,
+    current: usize,
+}
+
+// Impl
+
+impl Workspace {
+    pub fn new(name: &str, layouts: Vec<Box<dyn Layout>>, gaps: Gaps) -> Workspace {
+        Workspace {
+            name: name.to_string(),
+            clients: Vec::new(),
+            focused: None,
+            floating: Vec::new(),
+            layouts,
+            layout: 0,
+            gaps,
+            default_gaps: gaps,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn clients(&self) -> &[Window] {
+        &self.clients
+    }
+
+    pub fn focused(&self) -> Option<&Window> {
+        self.focused.as_ref()
+    }
+
+    pub fn floating(&self) -> &[Window] {
+        &self.floating
+    }
+
+    pub fn tiled(&self) -> Vec<Window> {
+        self.clients
+            .iter()
+            .filter(|win| !self.is_floating(win))
+            .copied()
+            .collect()
+    }
+
+    pub fn contains(&self, win: &Window) -> bool {
+        self.clients.contains(win)
+    }
+
+    pub fn is_floating(&self, win: &Window) -> bool {
+        self.floating.contains(win)
+    }
+
+    pub fn add(&mut self, win: Window, floating: bool) {
+        if floating {
+            self.floating.push(win);
+        } else {
+            for layout in &mut self.layouts {
+                layout.client_added(&win, self.focused.as_ref());
+            }
+        }
+        self.clients.push(win);
+        self.focused = Some(win);
+    }
+
+    pub fn remove(&mut self, win: &Window) {
+        let pos = match self.clients.iter().position(|w| w == win) {
+            Some(pos) => pos,
+            None => return,
+        };
+
+        self.clients.remove(pos);
+        if self.is_floating(win) {
+            self.floating.retain(|w| w != win);
+        } else {
+            for layout in &mut self.layouts {
+                layout.client_removed(win);
+            }
+        }
+
+        // Hand the focus to the neighbour of the removed window.
+        if self.focused.as_ref() == Some(win) {
+            self.focused = self
+                .clients
+                .get(pos.min(self.clients.len().saturating_sub(1)))
+                .copied();
+        }
+    }
+
+    pub fn focus(&mut self, win: &Window) {
+        if self.contains(win) {
+            self.focused = Some(*win);
+        }
+    }
+
+    // Move a client between the floating layer and the layout.
+    pub fn set_floating(&mut self, win: &Window, floating: bool) {
+        if !self.contains(win) || self.is_floating(win) == floating {
+            return;
+        }
+
+        if floating {
+            for layout in &mut self.layouts {
+                layout.client_removed(win);
+            }
+            self.floating.push(*win);
+        } else {
+            self.floating.retain(|w| w != win);
+            for layout in &mut self.layouts {
+                layout.client_added(win, None);
+            }
+        }
+    }
+
+    pub fn focus_next(&mut self) -> Option<&Window> {
+        self.focus_offset(1)
+    }
+
+    pub fn focus_prev(&mut self) -> Option<&Window> {
+        self.focus_offset(self.clients.len().saturating_sub(1))
+    }
+
+    // Trade the places of two tiled clients.
+    pub fn swap(&mut self, a: &Window, b: &Window) {
+        if a == b || self.is_floating(a) || self.is_floating(b) {
+            return;
+        }
+
+        let pos_a = self.clients.iter().position(|w| w == a);
+        let pos_b = self.clients.iter().position(|w| w == b);
+        if let (Some(pos_a), Some(pos_b)) = (pos_a, pos_b) {
+            self.clients.swap(pos_a, pos_b);
+            for layout in &mut self.layouts {
+                layout.client_swapped(a, b);
+            }
+        }
+    }
+
+    pub fn swap_next(&mut self) {
+        self.swap_offset(1);
+    }
+
+    pub fn swap_prev(&mut self) {
+        let len = self.tiled().len();
+        self.swap_offset(len.saturating_sub(1));
+    }
+
+    pub fn show(&self, conn: &Connection) {
+        for win in &self.clients {
+            conn.window_map(win);
+        }
+    }
+
+    pub fn hide(&self, conn: &Connection) {
+        for win in &self.clients {
+            conn.window_unmap(win);
+        }
+    }
+
+    pub fn layout(&self) -> &dyn Layout {
+        self.layouts[self.layout].as_ref()
+    }
+
+    pub fn layout_mut(&mut self) -> &mut dyn Layout {
+        self.layouts[self.layout].as_mut()
+    }
+
+    // Returns whether a layout of that name exists on this workspace.
+    pub fn set_layout(&mut self, name: &str) -> bool {
+        match self.layouts.iter().position(|layout| layout.name() == name) {
+            Some(idx) => {
+                self.layout = idx;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn next_layout(&mut self) {
+        self.layout = (self.layout + 1) % self.layouts.len();
+    }
+
+    pub fn prev_layout(&mut self) {
+        self.layout = (self.layout + self.layouts.len() - 1) % self.layouts.len();
+    }
+
+    pub fn layout_message(&mut self, msg: &LayoutMessage) -> bool {
+        let focused = self.focused.as_ref();
+        self.layouts[self.layout].handle(msg, focused)
+    }
+
+    pub fn inc_gaps(&mut self, outer: i16, inner: i16) {
+        let resize = |gap: u16, size: i16| (i32::from(gap) + i32::from(size)).max(0) as u16;
+        self.gaps.outer = resize(self.gaps.outer, outer);
+        self.gaps.inner = resize(self.gaps.inner, inner);
+    }
+
+    pub fn reset_gaps(&mut self) {
+        self.gaps = self.default_gaps;
+    }
+
+    // Gaps from a reloaded config replace any changed at runtime.
+    pub fn set_default_gaps(&mut self, gaps: Gaps) {
+        self.gaps = gaps;
+        self.default_gaps = gaps;
+    }
+
+    // Swap in a new set of layouts. The tiled clients are added again in
+    // order and the shown layout is kept if it is still there.
+    pub fn set_layouts(&mut self, layouts: Vec<Box<dyn Layout>>) {
+        let name = self.layout().name().to_string();
+        self.layouts = layouts;
+        self.layout = 0;
+        self.set_layout(&name);
+
+        let mut prev = None;
+        for win in self.tiled() {
+            for layout in &mut self.layouts {
+                layout.client_added(&win, prev.as_ref());
+            }
+            prev = Some(win);
+        }
+    }
+
+    pub fn render(&self, conn: &Connection, screen: &Geometry, border: u32) {
+        self.layout()
+            .render(conn, screen, &self.gaps, border, &self.tiled());
+    }
+
+    // Private
+    fn focus_offset(&mut self, offset: usize) -> Option<&Window> {
+        let len = self.clients.len();
+        let pos = self
+            .focused
+            .and_then(|win| self.clients.iter().position(|w| *w == win))?;
+
+        self.focused = Some(self.clients[(pos + offset) % len]);
+        self.focused.as_ref()
+    }
+
+    // The focused client moves, among the tiled ones only.
+    fn swap_offset(&mut self, offset: usize) {
+        let tiled = self.tiled();
+        let pos = match self
+            .focused
+            .and_then(|win| tiled.iter().position(|w| *w == win))
+        {
+            Some(pos) => pos,
+            None => return,
+        };
+
+        self.swap(&tiled[pos], &tiled[(pos + offset) % tiled.len()]);
+    }
+}
+
+impl WorkspaceSet {
+    pub fn new<F, G>(names: &[String], layouts: F, gaps: G) -> WorkspaceSet
+    where
+        F: Fn() -> Vec<Box<dyn Layout>>,
+        G: Fn(&str) -> Gaps,
+    {
+        WorkspaceSet {
+            workspaces: names
+                .iter()
+                .map(|name| Workspace::new(name, layouts(), gaps(name)))
+                .collect(),
+            current: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.workspaces.len()
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.workspaces.iter().map(|ws| ws.name()).collect()
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut Workspace> {
+        self.workspaces.iter_mut()
+    }
+
+    pub fn index(&self) -> usize {
+        self.current
+    }
+
+    pub fn current(&self) -> &Workspace {
+        &self.workspaces[self.current]
+    }
+
+    pub fn current_mut(&mut self) -> &mut Workspace {
+        &mut self.workspaces[self.current]
+    }
+
+    pub fn get_mut(&mut self, idx: usize) -> Option<&mut Workspace> {
+        self.workspaces.get_mut(idx)
+    }
+
+    // Index of the workspace holding the given window.
+    pub fn find(&self, win: &Window) -> Option<usize> {
+        self.workspaces.iter().position(|ws| ws.contains(win))
+    }
+
+    pub fn position(&self, name: &str) -> Option<usize> {
+        self.workspaces.iter().position(|ws| ws.name() == name)
+    }
+
+    pub fn select(&mut self, idx: usize) {
+        if idx < self.workspaces.len() {
+            self.current = idx;
+        }
+    }
+
+    pub fn next_index(&self) -> usize {
+        (self.current + 1) % self.workspaces.len()
+    }
+
+    pub fn prev_index(&self) -> usize {
+        (self.current + self.workspaces.len() - 1) % self.workspaces.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layouts::{self, windows, SCREEN};
+
+    fn workspace(names: &[&str]) -> Workspace {
+        Workspace::new("1", layout_list(names), Gaps::new(0, 0, false))
+    }
+
+    fn layout_list(names: &[&str]) -> Vec<Box<dyn Layout>> {
+        names
+            .iter()
+            .map(|name| layouts::from_name(name).unwrap())
+            .collect()
+    }
+
+    // Whether the shown layout found a place of its own for every tiled client.
+    fn all_placed(ws: &Workspace) -> bool {
+        let tiled = ws.tiled();
+        let geos = ws.layout().arrange(&SCREEN, &tiled);
+        tiled.len() < 2 || geos.iter().all(|geo| *geo != SCREEN)
+    }
+
+    #[test]
+    fn remove_hands_the_focus_to_the_neighbour() {
+        let wins = windows(3);
+        let mut ws = workspace(&["tile"]);
+        for win in &wins {
+            ws.add(*win, false);
+        }
+
+        ws.focus(&wins[1]);
+        ws.remove(&wins[1]);
+        assert_eq!(ws.clients(), &[wins[0], wins[2]]);
+        assert_eq!(ws.focused(), Some(&wins[2]));
+
+        // The last one hands it back.
+        ws.remove(&wins[2]);
+        assert_eq!(ws.focused(), Some(&wins[0]));
+
+        ws.remove(&wins[0]);
+        assert!(ws.clients().is_empty());
+        assert_eq!(ws.focused(), None);
+    }
+
+    #[test]
+    fn remove_keeps_the_focus_elsewhere() {
+        let wins = windows(3);
+        let mut ws = workspace(&["tile"]);
+        for win in &wins {
+            ws.add(*win, false);
+        }
+
+        ws.remove(&wins[0]);
+        assert_eq!(ws.focused(), Some(&wins[2]));
+
+        // Unknown windows are ignored.
+        ws.remove(&wins[0]);
+        assert_eq!(ws.clients(), &[wins[1], wins[2]]);
+    }
+
+    #[test]
+    fn focus_wraps_around() {
+        let wins = windows(3);
+        let mut ws = workspace(&["tile"]);
+        assert_eq!(ws.focus_next(), None);
+
+        for win in &wins {
+            ws.add(*win, false);
+        }
+
+        assert_eq!(ws.focus_next(), Some(&wins[0]));
+        assert_eq!(ws.focus_prev(), Some(&wins[2]));
+        assert_eq!(ws.focus_prev(), Some(&wins[1]));
+    }
+
+    #[test]
+    fn swaps_wrap_around_the_tiled_clients() {
+        let wins = windows(4);
+        let mut ws = workspace(&["tile"]);
+        for win in &wins {
+            ws.add(*win, *win == wins[2]);
+        }
+
+        ws.swap_next();
+        assert_eq!(ws.clients(), &[wins[3], wins[1], wins[2], wins[0]]);
+        assert_eq!(ws.focused(), Some(&wins[3]));
+
+        ws.swap_prev();
+        assert_eq!(ws.clients(), &wins[..]);
+
+        // Floating clients stay where they are.
+        ws.focus(&wins[2]);
+        ws.swap_next();
+        assert_eq!(ws.clients(), &wins[..]);
+    }
+
+    #[test]
+    fn floating_clients_leave_the_layout() {
+        let wins = windows(3);
+        let mut ws = workspace(&["bsp"]);
+        for win in &wins {
+            ws.add(*win, false);
+        }
+
+        ws.set_floating(&wins[1], true);
+        assert_eq!(ws.floating(), &[wins[1]]);
+        assert_eq!(ws.tiled(), vec![wins[0], wins[2]]);
+        assert!(all_placed(&ws));
+
+        ws.set_floating(&wins[1], false);
+        assert!(ws.floating().is_empty());
+        assert_eq!(ws.tiled(), wins);
+        assert!(all_placed(&ws));
+
+        ws.set_floating(&wins[1], true);
+        ws.remove(&wins[1]);
+        assert!(ws.floating().is_empty());
+        assert_eq!(ws.tiled(), vec![wins[0], wins[2]]);
+    }
+
+    #[test]
+    fn floating_needs_a_client() {
+        let wins = windows(2);
+        let mut ws = workspace(&["tile"]);
+        ws.add(wins[0], false);
+
+        ws.set_floating(&wins[1], true);
+        assert!(ws.floating().is_empty());
+        assert_eq!(ws.tiled(), vec![wins[0]]);
+    }
+
+    #[test]
+    fn set_layouts_keeps_the_shown_layout_and_the_clients() {
+        let wins = windows(3);
+        let mut ws = workspace(&["tile", "bsp"]);
+        assert!(ws.set_layout("bsp"));
+        for win in &wins {
+            ws.add(*win, *win == wins[1]);
+        }
+
+        ws.set_layouts(layout_list(&["grid", "bsp"]));
+        assert_eq!(ws.layout().name(), "bsp");
+        assert_eq!(ws.tiled(), vec![wins[0], wins[2]]);
+        assert!(all_placed(&ws));
+
+        // The first one is shown when the old one is gone.
+        ws.set_layouts(layout_list(&["stack", "tile"]));
+        assert_eq!(ws.layout().name(), "stack");
+        assert_eq!(ws.floating(), &[wins[1]]);
+    }
+}
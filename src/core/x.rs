@@ -24,13 +24,14 @@
  */
 // crate
 use crate::core::keys;
-use crate::core::layout::Layout;
+use crate::core::layout::Geometry;
 
 // std
-use std::collections::HashMap;
+use std::cell::Cell;
+use std::collections::{HashMap, HashSet};
 
 // thirdparty
-use xcb::randr;
+use x11::keysym;
 use xcb_util::keysyms::KeySymbols;
 use xcb_util::{ewmh, icccm};
 
@@ -40,6 +41,8 @@ pub type Result<T> = std::result::Result<T, failure::Error>;
 // Macros
 macro_rules! atoms {
     ( $( $name:ident ),+ ) => {
+        // Named like the atoms themselves.
+        #[allow(non_snake_case)]
         struct Atoms {
             $(
                 pub $name: xcb::Atom
@@ -59,9 +62,10 @@ macro_rules! atoms {
     ( $( $name:ident ),+ , ) => (atoms!($( $name ),+);)
 }
 
-atoms!(WM_DELETE_WINDOW, WM_PROTOCOLS,);
+atoms!(WM_DELETE_WINDOW, WM_PROTOCOLS, UTF8_STRING, _VOID_MODE,);
 
 // Enum
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum WindowType {
     Desktop,
     Dock,
@@ -79,6 +83,7 @@ pub enum WindowType {
     Normal,
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum WindowState {
     Modal,
     Sticky,
@@ -95,6 +100,7 @@ pub enum WindowState {
 }
 
 // Structs
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub struct Window(pub xcb::Window);
 
 pub struct Connection {
@@ -102,6 +108,8 @@ pub struct Connection {
     root: Window,
     atoms: Atoms,
     id: i32,
+    // The modifier Num Lock sits on, it differs between keymaps.
+    numlock: Cell<keys::ModMask>,
     window_type_lookup: HashMap<xcb::Atom, WindowType>,
     window_state_lookup: HashMap<xcb::Atom, WindowState>,
 }
@@ -131,11 +139,46 @@ impl Connection {
             .root();
         let atoms = Atoms::new(&conn)?;
 
+        types.insert(conn.WM_WINDOW_TYPE_DESKTOP(), WindowType::Desktop);
+        types.insert(conn.WM_WINDOW_TYPE_DOCK(), WindowType::Dock);
+        types.insert(conn.WM_WINDOW_TYPE_TOOLBAR(), WindowType::Toolbar);
+        types.insert(conn.WM_WINDOW_TYPE_MENU(), WindowType::Menu);
+        types.insert(conn.WM_WINDOW_TYPE_UTILITY(), WindowType::Utility);
+        types.insert(conn.WM_WINDOW_TYPE_SPLASH(), WindowType::Splash);
+        types.insert(conn.WM_WINDOW_TYPE_DIALOG(), WindowType::Dialog);
+        types.insert(
+            conn.WM_WINDOW_TYPE_DROPDOWN_MENU(),
+            WindowType::DropdownMenu,
+        );
+        types.insert(conn.WM_WINDOW_TYPE_POPUP_MENU(), WindowType::PopupMenu);
+        types.insert(conn.WM_WINDOW_TYPE_TOOLTIP(), WindowType::Tooltip);
+        types.insert(conn.WM_WINDOW_TYPE_NOTIFICATION(), WindowType::Notification);
+        types.insert(conn.WM_WINDOW_TYPE_COMBO(), WindowType::Combo);
+        types.insert(conn.WM_WINDOW_TYPE_DND(), WindowType::Dnd);
+        types.insert(conn.WM_WINDOW_TYPE_NORMAL(), WindowType::Normal);
+
+        states.insert(conn.WM_STATE_MODAL(), WindowState::Modal);
+        states.insert(conn.WM_STATE_STICKY(), WindowState::Sticky);
+        states.insert(conn.WM_STATE_MAXIMIZED_VERT(), WindowState::MaximizedVert);
+        states.insert(conn.WM_STATE_MAXIMIZED_HORZ(), WindowState::MaximizedHorz);
+        states.insert(conn.WM_STATE_SHADED(), WindowState::Shaded);
+        states.insert(conn.WM_STATE_SKIP_TASKBAR(), WindowState::SkipTaskbar);
+        states.insert(conn.WM_STATE_SKIP_PAGER(), WindowState::SkipPager);
+        states.insert(conn.WM_STATE_HIDDEN(), WindowState::Hidden);
+        states.insert(conn.WM_STATE_FULLSCREEN(), WindowState::Fullscreen);
+        states.insert(conn.WM_STATE_ABOVE(), WindowState::Above);
+        states.insert(conn.WM_STATE_BELOW(), WindowState::Below);
+        states.insert(
+            conn.WM_STATE_DEMANDS_ATTENTION(),
+            WindowState::DemandsAttention,
+        );
+
         Ok(Connection {
             conn,
             root: Window(root),
             atoms,
             id,
+            numlock: Cell::new(0),
             window_type_lookup: types,
             window_state_lookup: states,
         })
@@ -151,8 +194,26 @@ impl Connection {
         Ok(win_vec)
     }
 
+    // Whether a window found at startup should be managed. Skips unmapped
+    // windows and override-redirect ones like menus and tooltips.
+    pub fn window_is_adoptable(&self, win: &Window) -> bool {
+        match xcb::get_window_attributes(&self.conn, win.get()).get_reply() {
+            Ok(attr) => {
+                !attr.override_redirect() && attr.map_state() == xcb::MAP_STATE_VIEWABLE as u8
+            }
+            Err(_) => false,
+        }
+    }
+
+    pub fn window_is_viewable(&self, win: &Window) -> bool {
+        match xcb::get_window_attributes(&self.conn, win.get()).get_reply() {
+            Ok(attr) => attr.map_state() == xcb::MAP_STATE_VIEWABLE as u8,
+            Err(_) => false,
+        }
+    }
+
     // Check if the WM is already running. Register Events.
-    pub fn check_wm(&self, handler: &keys::KeyHandlers) -> Result<()> {
+    pub fn check_wm<T>(&self, handler: &keys::KeyHandlers<T>) -> Result<()> {
         xcb::change_window_attributes_checked(
             &self.conn,
             self.root.get(),
@@ -162,9 +223,46 @@ impl Connection {
             )],
         )
         .request_check()?;
+
+        self.grab_keys(handler);
         Ok(())
     }
 
+    // Drop all key grabs on the root window and grab the bound keys again,
+    // e.g. after the keyboard mapping changed.
+    pub fn grab_keys<T>(&self, handler: &keys::KeyHandlers<T>) {
+        self.numlock.set(self.modifier_mask(keysym::XK_Num_Lock));
+        xcb::ungrab_key(
+            &self.conn,
+            xcb::GRAB_ANY as u8,
+            self.root.get(),
+            xcb::MOD_MASK_ANY as u16,
+        );
+        self.window_enable_keyevents(&self.root, handler);
+    }
+
+    // Only touch the grabs of keys bound in one of the keymaps but not in
+    // the other, so the rest stays grabbed while the config is reloaded.
+    pub fn regrab_keys<T, U>(&self, old: &keys::KeyHandlers<T>, new: &keys::KeyHandlers<U>) {
+        let old: HashSet<_> = old
+            .key_combos()
+            .iter()
+            .map(|key| (key.modmask, key.keysym))
+            .collect();
+        let new: HashSet<_> = new
+            .key_combos()
+            .iter()
+            .map(|key| (key.modmask, key.keysym))
+            .collect();
+
+        for (modmask, keysym) in old.difference(&new) {
+            self.window_grab_key(&self.root, *modmask, *keysym, false);
+        }
+        for (modmask, keysym) in new.difference(&old) {
+            self.window_grab_key(&self.root, *modmask, *keysym, true);
+        }
+    }
+
     // Window events
 
     pub fn window_root(&self) -> &Window {
@@ -199,16 +297,31 @@ impl Connection {
         }
     }
 
-    pub fn window_configure(&self, win: &Window, x: u32, y: u32, width: u32, height: u32) {
+    pub fn window_configure(&self, win: &Window, geo: &Geometry) {
         let val = [
-            (xcb::CONFIG_WINDOW_X as u16, x),
-            (xcb::CONFIG_WINDOW_Y as u16, y),
-            (xcb::CONFIG_WINDOW_WIDTH as u16, width),
-            (xcb::CONFIG_WINDOW_HEIGHT as u16, height),
+            (xcb::CONFIG_WINDOW_X as u16, geo.x as u32),
+            (xcb::CONFIG_WINDOW_Y as u16, geo.y as u32),
+            (xcb::CONFIG_WINDOW_WIDTH as u16, geo.width),
+            (xcb::CONFIG_WINDOW_HEIGHT as u16, geo.height),
         ];
         xcb::configure_window(&self.conn, win.get(), &val);
     }
 
+    pub fn window_set_border_width(&self, win: &Window, width: u32) {
+        let val = [(xcb::CONFIG_WINDOW_BORDER_WIDTH as u16, width)];
+        xcb::configure_window(&self.conn, win.get(), &val);
+    }
+
+    pub fn window_set_border_color(&self, win: &Window, pixel: u32) {
+        let val = [(xcb::CW_BORDER_PIXEL, pixel)];
+        xcb::change_window_attributes(&self.conn, win.get(), &val);
+    }
+
+    pub fn window_raise(&self, win: &Window) {
+        let val = [(xcb::CONFIG_WINDOW_STACK_MODE as u16, xcb::STACK_MODE_ABOVE)];
+        xcb::configure_window(&self.conn, win.get(), &val);
+    }
+
     pub fn window_map(&self, win: &Window) {
         xcb::map_window(&self.conn, win.get());
     }
@@ -217,30 +330,133 @@ impl Connection {
         xcb::unmap_window(&self.conn, win.get());
     }
 
-    pub fn window_geometry(&self, win: &Window) -> (u32, u32) {
-        let reply = xcb::get_geometry(&self.conn, win.get())
+    // None if the window is gone already.
+    pub fn window_geometry(&self, win: &Window) -> Option<Geometry> {
+        let reply = xcb::get_geometry(&self.conn, win.get()).get_reply().ok()?;
+
+        Some(Geometry::new(
+            i32::from(reply.x()),
+            i32::from(reply.y()),
+            u32::from(reply.width()),
+            u32::from(reply.height()),
+        ))
+    }
+
+    pub fn window_is_urgent(&self, win: &Window) -> bool {
+        icccm::get_wm_hints(&self.conn, win.get())
             .get_reply()
-            .unwrap();
+            .ok()
+            .and_then(|hints| hints.is_urgent())
+            .unwrap_or(false)
+    }
 
-        (u32::from(reply.width()), u32::from(reply.height()))
+    pub fn window_types(&self, win: &Window) -> Vec<WindowType> {
+        ewmh::get_wm_window_type(&self.conn, win.get())
+            .get_reply()
+            .map(|reply| {
+                reply
+                    .atoms()
+                    .iter()
+                    .filter_map(|atom| self.window_type_lookup.get(atom).copied())
+                    .collect()
+            })
+            .unwrap_or_default()
     }
 
-    pub fn window_enable_keyevents(&self, win: &Window, key_handlers: &keys::KeyHandlers) {
-        let ksym = KeySymbols::new(&self.conn);
+    pub fn window_states(&self, win: &Window) -> Vec<WindowState> {
+        ewmh::get_wm_state(&self.conn, win.get())
+            .get_reply()
+            .map(|reply| {
+                reply
+                    .atoms()
+                    .iter()
+                    .filter_map(|atom| self.window_state_lookup.get(atom).copied())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    // Instance and class from WM_CLASS, empty if unset.
+    pub fn window_class(&self, win: &Window) -> (String, String) {
+        icccm::get_wm_class(&self.conn, win.get())
+            .get_reply()
+            .map(|reply| (reply.instance().to_string(), reply.class().to_string()))
+            .unwrap_or_default()
+    }
+
+    // _NET_WM_NAME, or WM_NAME for clients that only set that.
+    pub fn window_title(&self, win: &Window) -> String {
+        if let Ok(reply) = ewmh::get_wm_name(&self.conn, win.get()).get_reply() {
+            return reply.string().to_string();
+        }
+
+        icccm::get_wm_name(&self.conn, win.get())
+            .get_reply()
+            .map(|reply| reply.name().to_string())
+            .unwrap_or_default()
+    }
+
+    pub fn window_transient_for(&self, win: &Window) -> Option<Window> {
+        let reply = xcb::get_property(
+            &self.conn,
+            false,
+            win.get(),
+            xcb::ATOM_WM_TRANSIENT_FOR,
+            xcb::ATOM_WINDOW,
+            0,
+            1,
+        )
+        .get_reply()
+        .ok()?;
+
+        reply
+            .value::<xcb::Window>()
+            .first()
+            .filter(|&&parent| parent != xcb::NONE)
+            .map(|&parent| Window(parent))
+    }
+
+    // Every binding is grabbed once per lock state, so Caps Lock and
+    // Num Lock do not get in the way.
+    pub fn window_enable_keyevents<T>(&self, win: &Window, key_handlers: &keys::KeyHandlers<T>) {
         for key in key_handlers.key_combos() {
-            match ksym.get_keycode(key.keysym).next() {
-                Some(keycode) => {
+            self.window_grab_key(win, key.modmask, key.keysym, true);
+        }
+    }
+
+    // Grab or ungrab a single binding in every lock state.
+    pub fn window_grab_key(
+        &self,
+        win: &Window,
+        modmask: keys::ModMask,
+        keysym: keys::Key,
+        grab: bool,
+    ) {
+        let ksym = KeySymbols::new(&self.conn);
+        let numlock = self.numlock.get();
+        let locks = [0, xcb::MOD_MASK_LOCK, numlock, xcb::MOD_MASK_LOCK | numlock];
+
+        let keycodes: Vec<xcb::Keycode> = ksym.get_keycode(keysym).collect();
+        if keycodes.is_empty() {
+            println!("[E] Could not get keycode: {}", keys::keysym_name(keysym));
+        }
+
+        for keycode in keycodes {
+            for lock in &locks {
+                let modifiers = (modmask | lock) as u16;
+                if grab {
                     xcb::grab_key(
                         &self.conn,
                         false,
                         win.get(),
-                        key.modmask as u16,
+                        modifiers,
                         keycode,
                         xcb::GRAB_MODE_ASYNC as u8,
                         xcb::GRAB_MODE_ASYNC as u8,
                     );
+                } else {
+                    xcb::ungrab_key(&self.conn, keycode, win.get(), modifiers);
                 }
-                None => println!("[E] Could not get keycode: {}", key.keysym),
             }
         }
     }
@@ -248,7 +464,9 @@ impl Connection {
     pub fn window_enable_tracking(&self, win: &Window) {
         let val = [(
             xcb::CW_EVENT_MASK,
-            xcb::EVENT_MASK_ENTER_WINDOW | xcb::EVENT_MASK_STRUCTURE_NOTIFY,
+            xcb::EVENT_MASK_ENTER_WINDOW
+                | xcb::EVENT_MASK_STRUCTURE_NOTIFY
+                | xcb::EVENT_MASK_PROPERTY_CHANGE,
         )];
         xcb::change_window_attributes(&self.conn, win.get(), &val);
     }
@@ -272,13 +490,14 @@ impl Connection {
         ewmh::set_active_window(&self.conn, self.id, xcb::NONE);
     }
 
-    pub fn window_focused(&self) {}
+    pub fn screen_geometry(&self) -> Geometry {
+        self.window_geometry(self.window_root())
+            .expect("[E] Could not get the screen geometry.")
+    }
 
-    pub fn get_screen_width(&self) -> u16 {
-        randr::get_screen_size_range(&self.conn, self.window_root().get())
-            .get_reply()
-            .unwrap()
-            .max_width()
+    // Wait until the server handled everything sent so far.
+    pub fn sync(&self) {
+        let _ = xcb::get_input_focus(&self.conn).get_reply();
     }
 
     pub fn flush(&self) {
@@ -286,10 +505,73 @@ impl Connection {
     }
 
     pub fn get_event_loop(&self) -> super::event::EventLoop<'_> {
-        super::event::EventLoop { conn: self }
+        super::event::EventLoop::new(self)
+    }
+
+    // Take every key while a keymap mode is active.
+    pub fn grab_keyboard(&self) {
+        xcb::grab_keyboard(
+            &self.conn,
+            false,
+            self.root.get(),
+            xcb::CURRENT_TIME,
+            xcb::GRAB_MODE_ASYNC as u8,
+            xcb::GRAB_MODE_ASYNC as u8,
+        );
+    }
+
+    pub fn ungrab_keyboard(&self) {
+        xcb::ungrab_keyboard(&self.conn, xcb::CURRENT_TIME);
+    }
+
+    // Status bars read the active mode from _VOID_MODE on the root window.
+    pub fn set_mode(&self, mode: Option<&str>) {
+        match mode {
+            Some(name) => {
+                xcb::change_property(
+                    &self.conn,
+                    xcb::PROP_MODE_REPLACE as u8,
+                    self.root.get(),
+                    self.atoms._VOID_MODE,
+                    self.atoms.UTF8_STRING,
+                    8,
+                    name.as_bytes(),
+                );
+            }
+            None => {
+                xcb::delete_property(&self.conn, self.root.get(), self.atoms._VOID_MODE);
+            }
+        }
+    }
+
+    // Caps Lock and Num Lock, which bindings do not care about.
+    pub fn lock_mask(&self) -> keys::ModMask {
+        xcb::MOD_MASK_LOCK | self.numlock.get()
+    }
+
+    // The modifier one of the keycodes of `keysym` is mapped to, if any.
+    pub fn modifier_mask(&self, keysym: keys::Key) -> keys::ModMask {
+        let reply = match xcb::get_modifier_mapping(&self.conn).get_reply() {
+            Ok(reply) => reply,
+            Err(_) => return 0,
+        };
+        let ksym = KeySymbols::new(&self.conn);
+        let keycodes: Vec<xcb::Keycode> = ksym.get_keycode(keysym).collect();
+        let per_modifier = usize::from(reply.keycodes_per_modifier());
+
+        if per_modifier == 0 {
+            return 0;
+        }
+
+        reply
+            .keycodes()
+            .chunks(per_modifier)
+            .position(|mapped| mapped.iter().any(|code| keycodes.contains(code)))
+            .map_or(0, |modifier| 1 << modifier)
     }
 
     // Private
+
     fn query_protocols(&self, win: &Window) -> Result<Vec<xcb::Atom>> {
         let reply =
             icccm::get_wm_protocols(&self.conn, win.get(), self.atoms.WM_PROTOCOLS).get_reply()?;
@@ -0,0 +1,572 @@
+/*
+ * Copyright (c) 2020, Florian Büstgens
+ * All rights reserved.
+ *
+ * Redistribution and use in source and binary forms, with or without
+ * modification, are permitted provided that the following conditions are met:
+ *     1. Redistributions of source code must retain the above copyright
+ *        notice, this list of conditions and the following disclaimer.
+ *
+ *     2. Redistributions in binary form must reproduce the above copyright notice,
+ *        this list of conditions and the following disclaimer in the
+ *        documentation and/or other materials provided with the distribution.
+ *
+ * THIS SOFTWARE IS PROVIDED BY <copyright holder> ''AS IS'' AND ANY
+ * EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+ * WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+ * DISCLAIMED. IN NO EVENT SHALL <copyright holder> BE LIABLE FOR ANY
+ * DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+ * (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+ * LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND
+ * ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+ * (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+ * SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+ */
+
+use crate::core::layout::{Direction, Geometry, Layout, LayoutMessage};
+use crate::core::x;
+use crate::layouts::resize_msize;
+
+// Manual binary space partitioning. New clients split the focused one,
+// alternating between side by side and on top of each other unless the
+// next split was preselected.
+pub struct BspLayout {
+    root: Option<Node>,
+    presel: Option<(Direction, u16)>,
+}
+
+enum Node {
+    Leaf(x::Window),
+    Split {
+        // Side by side if set, on top of each other otherwise.
+        columns: bool,
+        // Share of the first child in percent.
+        ratio: u16,
+        first: Box<Node>,
+        second: Box<Node>,
+    },
+}
+
+impl BspLayout {
+    pub fn new() -> BspLayout {
+        BspLayout {
+            root: None,
+            presel: None,
+        }
+    }
+}
+
+impl Layout for BspLayout {
+    fn name(&self) -> &str {
+        "bsp"
+    }
+
+    fn inc_msize(&mut self, size: i16) {
+        if let Some(Node::Split { ratio, .. }) = self.root.as_mut() {
+            *ratio = resize_msize(*ratio, size);
+        }
+    }
+
+    fn inc_ssize(&mut self, size: i16) {
+        self.inc_msize(-size);
+    }
+
+    fn client_added(&mut self, win: &x::Window, focused: Option<&x::Window>) {
+        let presel = self.presel.take();
+        let root = match self.root.as_mut() {
+            Some(root) => root,
+            None => {
+                self.root = Some(Node::Leaf(*win));
+                return;
+            }
+        };
+
+        let target = match focused {
+            Some(focused) if root.contains(focused) => *focused,
+            _ => root.last_leaf(),
+        };
+
+        root.insert(&target, *win, presel, true);
+    }
+
+    fn client_removed(&mut self, win: &x::Window) {
+        match self.root {
+            Some(Node::Leaf(leaf)) if leaf == *win => self.root = None,
+            Some(ref mut root) => {
+                root.remove(win);
+            }
+            None => {}
+        }
+    }
+
+    fn client_swapped(&mut self, a: &x::Window, b: &x::Window) {
+        if let Some(root) = self.root.as_mut() {
+            root.swap(a, b);
+        }
+    }
+
+    fn handle(&mut self, msg: &LayoutMessage, focused: Option<&x::Window>) -> bool {
+        match *msg {
+            LayoutMessage::Preselect(dir, ratio) => {
+                self.presel = Some((dir, resize_msize(ratio, 0)));
+                return true;
+            }
+            LayoutMessage::CancelPreselect => {
+                self.presel = None;
+                return true;
+            }
+            _ => {}
+        }
+
+        let (root, focused) = match (self.root.as_mut(), focused) {
+            (Some(root), Some(focused)) => (root, focused),
+            _ => return false,
+        };
+
+        match *msg {
+            LayoutMessage::Rotate => root.parent_mut(focused).map(Node::rotate).is_some(),
+            LayoutMessage::FlipHorizontal => root
+                .parent_mut(focused)
+                .map(|node| node.flip(true))
+                .is_some(),
+            LayoutMessage::FlipVertical => root
+                .parent_mut(focused)
+                .map(|node| node.flip(false))
+                .is_some(),
+            LayoutMessage::ResizeSplit(dir, size) => root.resize(focused, dir, size),
+            _ => false,
+        }
+    }
+
+    fn arrange(&self, screen: &Geometry, clients: &[x::Window]) -> Vec<Geometry> {
+        let mut placed = Vec::with_capacity(clients.len());
+        if let Some(root) = self.root.as_ref() {
+            root.arrange(screen, clients, &mut placed);
+        }
+
+        clients
+            .iter()
+            .map(|win| {
+                placed
+                    .iter()
+                    .find(|(leaf, _)| leaf == win)
+                    .map(|(_, geo)| *geo)
+                    .unwrap_or(*screen)
+            })
+            .collect()
+    }
+}
+
+impl Node {
+    fn is_leaf(&self, win: &x::Window) -> bool {
+        match self {
+            Node::Leaf(leaf) => leaf == win,
+            Node::Split { .. } => false,
+        }
+    }
+
+    fn contains(&self, win: &x::Window) -> bool {
+        match self {
+            Node::Leaf(leaf) => leaf == win,
+            Node::Split { first, second, .. } => first.contains(win) || second.contains(win),
+        }
+    }
+
+    // Trade the windows of two leaves, the tree keeps its shape.
+    fn swap(&mut self, a: &x::Window, b: &x::Window) {
+        match self {
+            Node::Leaf(leaf) if leaf == a => *leaf = *b,
+            Node::Leaf(leaf) if leaf == b => *leaf = *a,
+            Node::Leaf(_) => {}
+            Node::Split { first, second, .. } => {
+                first.swap(a, b);
+                second.swap(a, b);
+            }
+        }
+    }
+
+    fn last_leaf(&self) -> x::Window {
+        match self {
+            Node::Leaf(leaf) => *leaf,
+            Node::Split { second, .. } => second.last_leaf(),
+        }
+    }
+
+    fn insert(
+        &mut self,
+        target: &x::Window,
+        win: x::Window,
+        presel: Option<(Direction, u16)>,
+        columns: bool,
+    ) -> bool {
+        if let Node::Leaf(leaf) = *self {
+            if leaf != *target {
+                return false;
+            }
+
+            let (columns, ratio, new_first) = match presel {
+                Some((Direction::Left, ratio)) => (true, 100 - ratio, true),
+                Some((Direction::Right, ratio)) => (true, ratio, false),
+                Some((Direction::Up, ratio)) => (false, 100 - ratio, true),
+                Some((Direction::Down, ratio)) => (false, ratio, false),
+                None => (columns, 50, false),
+            };
+
+            let (first, second) = if new_first {
+                (Node::Leaf(win), Node::Leaf(leaf))
+            } else {
+                (Node::Leaf(leaf), Node::Leaf(win))
+            };
+
+            *self = Node::Split {
+                columns,
+                ratio,
+                first: Box::new(first),
+                second: Box::new(second),
+            };
+            return true;
+        }
+
+        match self {
+            Node::Split { first, second, .. } => {
+                first.insert(target, win, presel, !columns)
+                    || second.insert(target, win, presel, !columns)
+            }
+            Node::Leaf(_) => false,
+        }
+    }
+
+    // Replace the parent of the removed leaf with its sibling.
+    fn remove(&mut self, win: &x::Window) -> bool {
+        let sibling = match self {
+            Node::Leaf(_) => return false,
+            Node::Split { first, second, .. } => {
+                if first.is_leaf(win) {
+                    std::mem::replace(second.as_mut(), Node::Leaf(*win))
+                } else if second.is_leaf(win) {
+                    std::mem::replace(first.as_mut(), Node::Leaf(*win))
+                } else {
+                    return first.remove(win) || second.remove(win);
+                }
+            }
+        };
+
+        *self = sibling;
+        true
+    }
+
+    fn parent_mut(&mut self, win: &x::Window) -> Option<&mut Node> {
+        let is_parent = match self {
+            Node::Leaf(_) => return None,
+            Node::Split { first, second, .. } => first.is_leaf(win) || second.is_leaf(win),
+        };
+
+        if is_parent {
+            return Some(self);
+        }
+
+        match self {
+            Node::Split { first, second, .. } => {
+                if first.contains(win) {
+                    first.parent_mut(win)
+                } else {
+                    second.parent_mut(win)
+                }
+            }
+            Node::Leaf(_) => None,
+        }
+    }
+
+    // Turn the subtree by 90 degrees clockwise.
+    fn rotate(&mut self) {
+        if let Node::Split {
+            columns,
+            ratio,
+            first,
+            second,
+        } = self
+        {
+            if !*columns {
+                std::mem::swap(first, second);
+                *ratio = 100 - *ratio;
+            }
+            *columns = !*columns;
+
+            first.rotate();
+            second.rotate();
+        }
+    }
+
+    // Mirror the subtree left to right, or top to bottom.
+    fn flip(&mut self, horizontal: bool) {
+        if let Node::Split {
+            columns,
+            ratio,
+            first,
+            second,
+        } = self
+        {
+            if *columns == horizontal {
+                std::mem::swap(first, second);
+                *ratio = 100 - *ratio;
+            }
+
+            first.flip(horizontal);
+            second.flip(horizontal);
+        }
+    }
+
+    // Move the closest boundary on the `dir` side of the window.
+    fn resize(&mut self, win: &x::Window, dir: Direction, size: i16) -> bool {
+        if let Node::Split {
+            columns,
+            ratio,
+            first,
+            second,
+        } = self
+        {
+            let in_first = first.contains(win);
+            if !in_first && !second.contains(win) {
+                return false;
+            }
+
+            let child = if in_first { first } else { second };
+            if child.resize(win, dir, size) {
+                return true;
+            }
+
+            let size = match (dir, *columns, in_first) {
+                (Direction::Right, true, true) | (Direction::Down, false, true) => size,
+                (Direction::Left, true, false) | (Direction::Up, false, false) => -size,
+                _ => return false,
+            };
+
+            *ratio = resize_msize(*ratio, size);
+            return true;
+        }
+
+        false
+    }
+
+    fn shows_any(&self, clients: &[x::Window]) -> bool {
+        match self {
+            Node::Leaf(leaf) => clients.contains(leaf),
+            Node::Split { first, second, .. } => {
+                first.shows_any(clients) || second.shows_any(clients)
+            }
+        }
+    }
+
+    // Leaves missing from `clients`, e.g. hidden by `limit`, give their
+    // space to the sibling.
+    fn arrange(
+        &self,
+        geo: &Geometry,
+        clients: &[x::Window],
+        placed: &mut Vec<(x::Window, Geometry)>,
+    ) {
+        match self {
+            Node::Leaf(leaf) => {
+                if clients.contains(leaf) {
+                    placed.push((*leaf, *geo));
+                }
+            }
+            Node::Split {
+                columns,
+                ratio,
+                first,
+                second,
+            } => {
+                let (a, b) = match (first.shows_any(clients), second.shows_any(clients)) {
+                    (true, true) if *columns => geo.divide_columns(*ratio),
+                    (true, true) => geo.divide_rows(*ratio),
+                    _ => (*geo, *geo),
+                };
+
+                first.arrange(&a, clients, placed);
+                second.arrange(&b, clients, placed);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layouts::{windows, SCREEN};
+
+    // Every client splits the one added before it.
+    fn layout(clients: &[x::Window]) -> BspLayout {
+        let mut layout = BspLayout::new();
+        let mut prev = None;
+        for win in clients {
+            layout.client_added(win, prev);
+            prev = Some(win);
+        }
+        layout
+    }
+
+    #[test]
+    fn splits_alternate() {
+        let clients = windows(3);
+        assert_eq!(
+            layout(&clients).arrange(&SCREEN, &clients),
+            vec![
+                Geometry::new(0, 0, 500, 800),
+                Geometry::new(500, 0, 500, 400),
+                Geometry::new(500, 400, 500, 400),
+            ]
+        );
+    }
+
+    #[test]
+    fn insert_splits_the_focused() {
+        let clients = windows(3);
+        let mut layout = layout(&clients[..2]);
+        layout.client_added(&clients[2], Some(&clients[0]));
+        assert_eq!(
+            layout.arrange(&SCREEN, &clients),
+            vec![
+                Geometry::new(0, 0, 500, 400),
+                Geometry::new(500, 0, 500, 800),
+                Geometry::new(0, 400, 500, 400),
+            ]
+        );
+    }
+
+    #[test]
+    fn preselect_places_the_next() {
+        let clients = windows(2);
+        let mut layout = layout(&clients[..1]);
+        assert!(layout.handle(&LayoutMessage::Preselect(Direction::Left, 30), None));
+        layout.client_added(&clients[1], Some(&clients[0]));
+        assert_eq!(
+            layout.arrange(&SCREEN, &clients),
+            vec![
+                Geometry::new(700, 0, 300, 800),
+                Geometry::new(0, 0, 700, 800)
+            ]
+        );
+    }
+
+    #[test]
+    fn remove_gives_the_space_to_the_sibling() {
+        let clients = windows(3);
+        let mut layout = layout(&clients);
+        layout.client_removed(&clients[1]);
+        assert_eq!(
+            layout.arrange(&SCREEN, &[clients[0], clients[2]]),
+            vec![
+                Geometry::new(0, 0, 500, 800),
+                Geometry::new(500, 0, 500, 800)
+            ]
+        );
+
+        layout.client_removed(&clients[0]);
+        layout.client_removed(&clients[2]);
+        assert!(layout.root.is_none());
+    }
+
+    #[test]
+    fn rotate_turns_clockwise() {
+        let clients = windows(3);
+        let mut layout = layout(&clients);
+        assert!(layout.handle(&LayoutMessage::Rotate, Some(&clients[1])));
+        assert_eq!(
+            layout.arrange(&SCREEN, &clients),
+            vec![
+                Geometry::new(0, 0, 500, 800),
+                Geometry::new(750, 0, 250, 800),
+                Geometry::new(500, 0, 250, 800),
+            ]
+        );
+
+        assert!(layout.handle(&LayoutMessage::Rotate, Some(&clients[0])));
+        assert_eq!(
+            layout.arrange(&SCREEN, &clients),
+            vec![
+                Geometry::new(0, 0, 1000, 400),
+                Geometry::new(0, 600, 1000, 200),
+                Geometry::new(0, 400, 1000, 200),
+            ]
+        );
+    }
+
+    #[test]
+    fn flip_only_swaps_along_its_axis() {
+        let clients = windows(3);
+        let mut layout = layout(&clients);
+        assert!(layout.handle(&LayoutMessage::FlipVertical, Some(&clients[1])));
+        assert_eq!(
+            layout.arrange(&SCREEN, &clients),
+            vec![
+                Geometry::new(0, 0, 500, 800),
+                Geometry::new(500, 400, 500, 400),
+                Geometry::new(500, 0, 500, 400),
+            ]
+        );
+
+        assert!(layout.handle(&LayoutMessage::FlipHorizontal, Some(&clients[0])));
+        assert_eq!(
+            layout.arrange(&SCREEN, &clients),
+            vec![
+                Geometry::new(500, 0, 500, 800),
+                Geometry::new(0, 400, 500, 400),
+                Geometry::new(0, 0, 500, 400),
+            ]
+        );
+    }
+
+    #[test]
+    fn resize_moves_the_closest_boundary() {
+        let clients = windows(3);
+        let mut layout = layout(&clients);
+        let resize = |dir, size| LayoutMessage::ResizeSplit(dir, size);
+
+        assert!(layout.handle(&resize(Direction::Right, 10), Some(&clients[0])));
+        assert!(layout.handle(&resize(Direction::Up, 25), Some(&clients[2])));
+        assert!(!layout.handle(&resize(Direction::Up, 10), Some(&clients[0])));
+        assert_eq!(
+            layout.arrange(&SCREEN, &clients),
+            vec![
+                Geometry::new(0, 0, 600, 800),
+                Geometry::new(600, 0, 400, 200),
+                Geometry::new(600, 200, 400, 600),
+            ]
+        );
+    }
+
+    #[test]
+    fn messages_need_a_focused_client() {
+        let clients = windows(2);
+        let mut layout = layout(&clients);
+        assert!(!layout.handle(&LayoutMessage::Rotate, None));
+        assert!(!BspLayout::new().handle(&LayoutMessage::Rotate, Some(&clients[0])));
+    }
+
+    #[test]
+    fn swap_keeps_the_shape() {
+        let clients = windows(2);
+        let mut layout = layout(&clients);
+        layout.client_swapped(&clients[0], &clients[1]);
+        assert_eq!(
+            layout.arrange(&SCREEN, &clients),
+            vec![
+                Geometry::new(500, 0, 500, 800),
+                Geometry::new(0, 0, 500, 800)
+            ]
+        );
+    }
+
+    #[test]
+    fn hidden_leaves_take_no_space() {
+        let clients = windows(3);
+        assert_eq!(
+            layout(&clients).arrange(&SCREEN, &clients[..2]),
+            vec![
+                Geometry::new(0, 0, 500, 800),
+                Geometry::new(500, 0, 500, 800)
+            ]
+        );
+    }
+}
@@ -0,0 +1,139 @@
+/*
+ * Copyright (c) 2020, Florian Büstgens
+ * All rights reserved.
+ *
+ * Redistribution and use in source and binary forms, with or without
+ * modification, are permitted provided that the following conditions are met:
+ *     1. Redistributions of source code must retain the above copyright
+ *        notice, this list of conditions and the following disclaimer.
+ *
+ *     2. Redistributions in binary form must reproduce the above copyright notice,
+ *        this list of conditions and the following disclaimer in the
+ *        documentation and/or other materials provided with the distribution.
+ *
+ * THIS SOFTWARE IS PROVIDED BY <copyright holder> ''AS IS'' AND ANY
+ * EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+ * WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+ * DISCLAIMED. IN NO EVENT SHALL <copyright holder> BE LIABLE FOR ANY
+ * DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+ * (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+ * LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND
+ * ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+ * (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+ * SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+ */
+
+use crate::core::layout::{Geometry, Layout};
+use crate::core::x;
+use crate::layouts::resize_msize;
+
+// Master column in the middle of the screen, the stack is split into a
+// column on each side. With a single stack client the master moves left.
+pub struct CenteredLayout {
+    nmaster: usize,
+    msize: u16,
+}
+
+impl CenteredLayout {
+    pub fn new() -> CenteredLayout {
+        CenteredLayout {
+            nmaster: 1,
+            msize: 50,
+        }
+    }
+}
+
+impl Layout for CenteredLayout {
+    fn name(&self) -> &str {
+        "centered"
+    }
+
+    fn inc_msize(&mut self, size: i16) {
+        self.msize = resize_msize(self.msize, size);
+    }
+
+    fn inc_ssize(&mut self, size: i16) {
+        self.msize = resize_msize(self.msize, -size);
+    }
+
+    fn inc_nmaster(&mut self, count: i16) {
+        self.nmaster = (self.nmaster as i32 + i32::from(count)).max(0) as usize;
+    }
+
+    fn arrange(&self, screen: &Geometry, clients: &[x::Window]) -> Vec<Geometry> {
+        let count = clients.len();
+        let nmaster = self.nmaster.min(count);
+        let nstack = count - nmaster;
+
+        if nmaster == 0 || nstack == 0 {
+            return screen.rows(count);
+        }
+
+        if nstack == 1 {
+            let (master, stack) = screen.divide_columns(self.msize);
+            let mut geos = master.rows(nmaster);
+            geos.push(stack);
+            return geos;
+        }
+
+        let mwidth = screen.width * u32::from(self.msize) / 100;
+        let lwidth = (screen.width - mwidth) / 2;
+        let rwidth = screen.width - mwidth - lwidth;
+        let left = Geometry::new(screen.x, screen.y, lwidth, screen.height);
+        let master = Geometry::new(screen.x + lwidth as i32, screen.y, mwidth, screen.height);
+        let right = Geometry::new(
+            screen.x + (lwidth + mwidth) as i32,
+            screen.y,
+            rwidth,
+            screen.height,
+        );
+
+        let nright = nstack - nstack / 2;
+        let mut geos = master.rows(nmaster);
+        geos.extend(right.rows(nright));
+        geos.extend(left.rows(nstack - nright));
+        geos
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layouts::{windows, SCREEN};
+
+    #[test]
+    fn master_in_the_middle() {
+        assert_eq!(
+            CenteredLayout::new().arrange(&SCREEN, &windows(4)),
+            vec![
+                Geometry::new(250, 0, 500, 800),
+                Geometry::new(750, 0, 250, 400),
+                Geometry::new(750, 400, 250, 400),
+                Geometry::new(0, 0, 250, 800),
+            ]
+        );
+    }
+
+    #[test]
+    fn master_moves_left_for_a_single_stack_client() {
+        assert_eq!(
+            CenteredLayout::new().arrange(&SCREEN, &windows(2)),
+            vec![
+                Geometry::new(0, 0, 500, 800),
+                Geometry::new(500, 0, 500, 800)
+            ]
+        );
+    }
+
+    #[test]
+    fn rows_without_stack() {
+        assert_eq!(
+            CenteredLayout::new().arrange(&SCREEN, &windows(1)),
+            vec![SCREEN]
+        );
+
+        let mut layout = CenteredLayout::new();
+        layout.inc_nmaster(-1);
+        assert_eq!(layout.arrange(&SCREEN, &windows(3)), SCREEN.rows(3));
+    }
+}
@@ -0,0 +1,172 @@
+/*
+ * Copyright (c) 2020, Florian Büstgens
+ * All rights reserved.
+ *
+ * Redistribution and use in source and binary forms, with or without
+ * modification, are permitted provided that the following conditions are met:
+ *     1. Redistributions of source code must retain the above copyright
+ *        notice, this list of conditions and the following disclaimer.
+ *
+ *     2. Redistributions in binary form must reproduce the above copyright notice,
+ *        this list of conditions and the following disclaimer in the
+ *        documentation and/or other materials provided with the distribution.
+ *
+ * THIS SOFTWARE IS PROVIDED BY <copyright holder> ''AS IS'' AND ANY
+ * EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+ * WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+ * DISCLAIMED. IN NO EVENT SHALL <copyright holder> BE LIABLE FOR ANY
+ * DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+ * (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+ * LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND
+ * ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+ * (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+ * SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+ */
+
+use crate::core::layout::{Geometry, Layout};
+use crate::core::x;
+use crate::layouts::resize_msize;
+
+// Each client takes a part of the remaining area, which is split
+// alternately side by side and on top of each other. Spiral turns
+// clockwise into the screen, dwindle always shrinks to the bottom right.
+pub struct SpiralLayout {
+    msize: u16,
+}
+
+pub struct DwindleLayout {
+    msize: u16,
+}
+
+impl SpiralLayout {
+    pub fn new() -> SpiralLayout {
+        SpiralLayout { msize: 50 }
+    }
+}
+
+impl DwindleLayout {
+    pub fn new() -> DwindleLayout {
+        DwindleLayout { msize: 50 }
+    }
+}
+
+impl Layout for SpiralLayout {
+    fn name(&self) -> &str {
+        "spiral"
+    }
+
+    fn inc_msize(&mut self, size: i16) {
+        self.msize = resize_msize(self.msize, size);
+    }
+
+    fn inc_ssize(&mut self, size: i16) {
+        self.msize = resize_msize(self.msize, -size);
+    }
+
+    fn arrange(&self, screen: &Geometry, clients: &[x::Window]) -> Vec<Geometry> {
+        fibonacci(screen, clients.len(), self.msize, true)
+    }
+}
+
+impl Layout for DwindleLayout {
+    fn name(&self) -> &str {
+        "dwindle"
+    }
+
+    fn inc_msize(&mut self, size: i16) {
+        self.msize = resize_msize(self.msize, size);
+    }
+
+    fn inc_ssize(&mut self, size: i16) {
+        self.msize = resize_msize(self.msize, -size);
+    }
+
+    fn arrange(&self, screen: &Geometry, clients: &[x::Window]) -> Vec<Geometry> {
+        fibonacci(screen, clients.len(), self.msize, false)
+    }
+}
+
+fn fibonacci(screen: &Geometry, count: usize, msize: u16, spiral: bool) -> Vec<Geometry> {
+    let mut geos = Vec::with_capacity(count);
+    let mut rest = *screen;
+
+    for i in 0..count {
+        if i == count - 1 {
+            geos.push(rest);
+            break;
+        }
+
+        let ratio = if i == 0 { msize } else { 50 };
+        let (first, second) = if i % 2 == 0 {
+            rest.divide_columns(ratio)
+        } else {
+            rest.divide_rows(ratio)
+        };
+
+        // Spiral places every other pair the other way round.
+        if spiral && i % 4 >= 2 {
+            geos.push(second);
+            rest = first;
+        } else {
+            geos.push(first);
+            rest = second;
+        }
+    }
+
+    geos
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layouts::{windows, SCREEN};
+
+    #[test]
+    fn spiral_turns_inwards() {
+        assert_eq!(
+            SpiralLayout::new().arrange(&SCREEN, &windows(4)),
+            vec![
+                Geometry::new(0, 0, 500, 800),
+                Geometry::new(500, 0, 500, 400),
+                Geometry::new(750, 400, 250, 400),
+                Geometry::new(500, 400, 250, 400),
+            ]
+        );
+    }
+
+    #[test]
+    fn dwindle_shrinks_to_the_bottom_right() {
+        assert_eq!(
+            DwindleLayout::new().arrange(&SCREEN, &windows(4)),
+            vec![
+                Geometry::new(0, 0, 500, 800),
+                Geometry::new(500, 0, 500, 400),
+                Geometry::new(500, 400, 250, 400),
+                Geometry::new(750, 400, 250, 400),
+            ]
+        );
+    }
+
+    #[test]
+    fn msize_only_sizes_the_first() {
+        let mut layout = DwindleLayout::new();
+        layout.inc_msize(10);
+        assert_eq!(
+            layout.arrange(&SCREEN, &windows(3)),
+            vec![
+                Geometry::new(0, 0, 600, 800),
+                Geometry::new(600, 0, 400, 400),
+                Geometry::new(600, 400, 400, 400),
+            ]
+        );
+    }
+
+    #[test]
+    fn few_clients() {
+        assert_eq!(SpiralLayout::new().arrange(&SCREEN, &[]), vec![]);
+        assert_eq!(
+            SpiralLayout::new().arrange(&SCREEN, &windows(1)),
+            vec![SCREEN]
+        );
+    }
+}
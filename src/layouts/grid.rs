@@ -0,0 +1,126 @@
+/*
+ * Copyright (c) 2020, Florian Büstgens
+ * All rights reserved.
+ *
+ * Redistribution and use in source and binary forms, with or without
+ * modification, are permitted provided that the following conditions are met:
+ *     1. Redistributions of source code must retain the above copyright
+ *        notice, this list of conditions and the following disclaimer.
+ *
+ *     2. Redistributions in binary form must reproduce the above copyright notice,
+ *        this list of conditions and the following disclaimer in the
+ *        documentation and/or other materials provided with the distribution.
+ *
+ * THIS SOFTWARE IS PROVIDED BY <copyright holder> ''AS IS'' AND ANY
+ * EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+ * WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+ * DISCLAIMED. IN NO EVENT SHALL <copyright holder> BE LIABLE FOR ANY
+ * DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+ * (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+ * LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND
+ * ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+ * (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+ * SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+ */
+
+use crate::core::layout::{Geometry, Layout};
+use crate::core::x;
+
+// Near-square grid. Columns further right take the clients that do not
+// divide evenly, so no cell is left empty.
+#[derive(Default)]
+pub struct GridLayout;
+
+impl GridLayout {
+    pub fn new() -> GridLayout {
+        GridLayout
+    }
+}
+
+impl Layout for GridLayout {
+    fn name(&self) -> &str {
+        "grid"
+    }
+
+    fn inc_msize(&mut self, _size: i16) {}
+
+    fn inc_ssize(&mut self, _size: i16) {}
+
+    fn arrange(&self, screen: &Geometry, clients: &[x::Window]) -> Vec<Geometry> {
+        let count = clients.len();
+        if count == 0 {
+            return Vec::new();
+        }
+
+        let mut ncols = 1;
+        while ncols * ncols < count {
+            ncols += 1;
+        }
+
+        let nrows = count / ncols;
+        let extra = count % ncols;
+
+        screen
+            .columns(ncols)
+            .into_iter()
+            .enumerate()
+            .flat_map(|(col, geo)| {
+                let rows = if col >= ncols - extra {
+                    nrows + 1
+                } else {
+                    nrows
+                };
+                geo.rows(rows)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layouts::{windows, SCREEN};
+
+    fn arrange(count: u32) -> Vec<Geometry> {
+        GridLayout::new().arrange(&SCREEN, &windows(count))
+    }
+
+    #[test]
+    fn one_and_two() {
+        assert_eq!(arrange(0), vec![]);
+        assert_eq!(arrange(1), vec![SCREEN]);
+        assert_eq!(
+            arrange(2),
+            vec![
+                Geometry::new(0, 0, 500, 800),
+                Geometry::new(500, 0, 500, 800)
+            ]
+        );
+    }
+
+    #[test]
+    fn last_column_takes_the_odd_one() {
+        assert_eq!(
+            arrange(3),
+            vec![
+                Geometry::new(0, 0, 500, 800),
+                Geometry::new(500, 0, 500, 400),
+                Geometry::new(500, 400, 500, 400),
+            ]
+        );
+    }
+
+    #[test]
+    fn no_empty_cells() {
+        assert_eq!(
+            arrange(5),
+            vec![
+                Geometry::new(0, 0, 333, 800),
+                Geometry::new(333, 0, 333, 400),
+                Geometry::new(333, 400, 333, 400),
+                Geometry::new(666, 0, 334, 400),
+                Geometry::new(666, 400, 334, 400),
+            ]
+        );
+    }
+}
@@ -24,5 +24,145 @@
  */
 
 // Public
+pub mod bsp;
+pub mod centered;
+pub mod fibonacci;
+pub mod grid;
+pub mod modifier;
+pub mod stack;
+pub mod threecol;
 pub mod tile;
+
+use crate::core::layout::Layout;
+use modifier::{Modified, Modifier};
+
+// Layouts are named plainly, like `tile`, or wrapped in modifiers, like
+// `gaps(8, mirror(tile))`.
+pub fn from_name(name: &str) -> Option<Box<dyn Layout>> {
+    let name = name.trim();
+    if let Some(open) = name.find('(') {
+        if !name.ends_with(')') {
+            return None;
+        }
+
+        let args = &name[open + 1..name.len() - 1];
+        return from_modifier(name[..open].trim(), args);
+    }
+
+    let layout: Box<dyn Layout> = match name {
+        "tile" => Box::new(tile::TileLayout::new()),
+        "stack" => Box::new(stack::StackLayout::new()),
+        "grid" => Box::new(grid::GridLayout::new()),
+        "spiral" => Box::new(fibonacci::SpiralLayout::new()),
+        "dwindle" => Box::new(fibonacci::DwindleLayout::new()),
+        "centered" => Box::new(centered::CenteredLayout::new()),
+        "threecol" => Box::new(threecol::ThreeColumnLayout::new()),
+        "bsp" => Box::new(bsp::BspLayout::new()),
+        _ => return None,
+    };
+
+    Some(layout)
+}
+
 // Private
+fn from_modifier(name: &str, args: &str) -> Option<Box<dyn Layout>> {
+    // Modifiers with a parameter take it before the wrapped layout.
+    let param = || {
+        let comma = args.find(',')?;
+        Some((args[..comma].trim(), &args[comma + 1..]))
+    };
+
+    let (modifier, inner) = match name {
+        "mirror" => (Modifier::Mirror, args),
+        "reflectx" => (Modifier::ReflectX, args),
+        "reflecty" => (Modifier::ReflectY, args),
+        "smartborders" => (Modifier::SmartBorders, args),
+        "gaps" => {
+            let (space, inner) = param()?;
+            (Modifier::Gaps(space.parse().ok()?), inner)
+        }
+        "limit" => {
+            let (count, inner) = param()?;
+            (Modifier::Limit(count.parse().ok()?), inner)
+        }
+        _ => return None,
+    };
+
+    let inner = from_name(inner)?;
+    Some(Box::new(Modified::new(modifier, inner)))
+}
+
+// Bounds of the master area in percent of the screen.
+const MSIZE_MIN: u16 = 5;
+const MSIZE_MAX: u16 = 95;
+
+fn resize_msize(msize: u16, size: i16) -> u16 {
+    (i32::from(msize) + i32::from(size))
+        .max(MSIZE_MIN.into())
+        .min(MSIZE_MAX.into()) as u16
+}
+
+// Fixtures for the layout tests.
+#[cfg(test)]
+pub const SCREEN: crate::core::layout::Geometry = crate::core::layout::Geometry {
+    x: 0,
+    y: 0,
+    width: 1000,
+    height: 800,
+};
+
+// Clients numbered from 1.
+#[cfg(test)]
+pub fn windows(count: u32) -> Vec<crate::core::x::Window> {
+    (1..=count).map(crate::core::x::Window).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn msize_stays_in_bounds() {
+        assert_eq!(resize_msize(50, 10), 60);
+        assert_eq!(resize_msize(50, -60), MSIZE_MIN);
+        assert_eq!(resize_msize(90, 20), MSIZE_MAX);
+    }
+
+    #[test]
+    fn layouts_by_name() {
+        let names = [
+            "tile", "stack", "grid", "spiral", "dwindle", "centered", "threecol", "bsp",
+        ];
+        for name in &names {
+            let layout = from_name(name).unwrap();
+            assert_eq!(layout.name(), *name);
+        }
+        assert!(from_name("nosuch").is_none());
+    }
+
+    #[test]
+    fn modifier_names_are_normalized() {
+        let name = |text| from_name(text).map(|layout| layout.name().to_string());
+        assert_eq!(name(" tile "), Some("tile".to_string()));
+        assert_eq!(name("gaps( 8 ,tile)"), Some("gaps(8, tile)".to_string()));
+        assert_eq!(
+            name("limit(2,mirror( bsp ))"),
+            Some("limit(2, mirror(bsp))".to_string())
+        );
+    }
+
+    #[test]
+    fn bad_modifiers_are_rejected() {
+        let bad = [
+            "mirror()",
+            "mirror(tile",
+            "gaps(tile)",
+            "gaps(x, tile)",
+            "limit(-1, tile)",
+            "blur(tile)",
+        ];
+        for name in &bad {
+            assert!(from_name(name).is_none(), "{}", name);
+        }
+    }
+}
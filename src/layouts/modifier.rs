@@ -0,0 +1,302 @@
+/*
+ * Copyright (c) 2020, Florian Büstgens
+ * All rights reserved.
+ *
+ * Redistribution and use in source and binary forms, with or without
+ * modification, are permitted provided that the following conditions are met:
+ *     1. Redistributions of source code must retain the above copyright
+ *        notice, this list of conditions and the following disclaimer.
+ *
+ *     2. Redistributions in binary form must reproduce the above copyright notice,
+ *        this list of conditions and the following disclaimer in the
+ *        documentation and/or other materials provided with the distribution.
+ *
+ * THIS SOFTWARE IS PROVIDED BY <copyright holder> ''AS IS'' AND ANY
+ * EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+ * WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+ * DISCLAIMED. IN NO EVENT SHALL <copyright holder> BE LIABLE FOR ANY
+ * DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+ * (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+ * LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND
+ * ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+ * (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+ * SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+ */
+
+use crate::core::layout::{Geometry, Layout, LayoutMessage};
+use crate::core::x;
+
+// Tweaks applied on top of another layout. They nest, so one layout can
+// be reused in many variants, e.g. `gaps(8, mirror(tile))`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Modifier {
+    // Swap the axes, a side by side layout is stacked on top instead.
+    Mirror,
+    // Flip left to right.
+    ReflectX,
+    // Flip top to bottom.
+    ReflectY,
+    // Space around every window.
+    Gaps(u16),
+    // No border while only one window is tiled.
+    SmartBorders,
+    // Only lay out the first n windows, the rest is moved off screen.
+    Limit(usize),
+}
+
+pub struct Modified {
+    name: String,
+    modifier: Modifier,
+    inner: Box<dyn Layout>,
+}
+
+impl Modified {
+    pub fn new(modifier: Modifier, inner: Box<dyn Layout>) -> Modified {
+        let name = match modifier {
+            Modifier::Mirror => format!("mirror({})", inner.name()),
+            Modifier::ReflectX => format!("reflectx({})", inner.name()),
+            Modifier::ReflectY => format!("reflecty({})", inner.name()),
+            Modifier::Gaps(space) => format!("gaps({}, {})", space, inner.name()),
+            Modifier::SmartBorders => format!("smartborders({})", inner.name()),
+            Modifier::Limit(count) => format!("limit({}, {})", count, inner.name()),
+        };
+
+        Modified {
+            name,
+            modifier,
+            inner,
+        }
+    }
+}
+
+impl Layout for Modified {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn inc_msize(&mut self, size: i16) {
+        self.inner.inc_msize(size);
+    }
+
+    fn inc_ssize(&mut self, size: i16) {
+        self.inner.inc_ssize(size);
+    }
+
+    fn inc_nmaster(&mut self, count: i16) {
+        self.inner.inc_nmaster(count);
+    }
+
+    fn client_added(&mut self, win: &x::Window, focused: Option<&x::Window>) {
+        self.inner.client_added(win, focused);
+    }
+
+    fn client_removed(&mut self, win: &x::Window) {
+        self.inner.client_removed(win);
+    }
+
+    fn client_swapped(&mut self, a: &x::Window, b: &x::Window) {
+        self.inner.client_swapped(a, b);
+    }
+
+    fn handle(&mut self, msg: &LayoutMessage, focused: Option<&x::Window>) -> bool {
+        self.inner.handle(msg, focused)
+    }
+
+    fn borders(&self, count: usize) -> bool {
+        match self.modifier {
+            Modifier::SmartBorders => count > 1 && self.inner.borders(count),
+            _ => self.inner.borders(count),
+        }
+    }
+
+    fn arrange(&self, screen: &Geometry, clients: &[x::Window]) -> Vec<Geometry> {
+        match self.modifier {
+            Modifier::Mirror => self
+                .inner
+                .arrange(&transpose(screen, screen), clients)
+                .iter()
+                .map(|geo| transpose(screen, geo))
+                .collect(),
+            Modifier::ReflectX => self
+                .inner
+                .arrange(screen, clients)
+                .into_iter()
+                .map(|geo| Geometry {
+                    x: 2 * screen.x + screen.width as i32 - geo.x - geo.width as i32,
+                    ..geo
+                })
+                .collect(),
+            Modifier::ReflectY => self
+                .inner
+                .arrange(screen, clients)
+                .into_iter()
+                .map(|geo| Geometry {
+                    y: 2 * screen.y + screen.height as i32 - geo.y - geo.height as i32,
+                    ..geo
+                })
+                .collect(),
+            Modifier::Gaps(space) => {
+                let half = u32::from(space / 2);
+                self.inner
+                    .arrange(&screen.shrink(half), clients)
+                    .iter()
+                    .map(|geo| geo.shrink(half))
+                    .collect()
+            }
+            Modifier::SmartBorders => self.inner.arrange(screen, clients),
+            Modifier::Limit(count) => {
+                let shown = count.min(clients.len());
+                let hidden = Geometry {
+                    x: screen.x - 2 * screen.width as i32,
+                    ..*screen
+                };
+
+                let mut geos = self.inner.arrange(screen, &clients[..shown]);
+                geos.resize(clients.len(), hidden);
+                geos
+            }
+        }
+    }
+}
+
+// Swap the axes of `geo` around the origin of `screen`.
+fn transpose(screen: &Geometry, geo: &Geometry) -> Geometry {
+    Geometry::new(
+        screen.x + (geo.y - screen.y),
+        screen.y + (geo.x - screen.x),
+        geo.height,
+        geo.width,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layouts::bsp::BspLayout;
+    use crate::layouts::tile::TileLayout;
+    use crate::layouts::{self, windows};
+
+    // Off the origin, to catch math that assumes it.
+    const SCREEN: Geometry = Geometry {
+        x: 100,
+        y: 50,
+        ..layouts::SCREEN
+    };
+
+    fn tile(modifier: Modifier) -> Modified {
+        Modified::new(modifier, Box::new(TileLayout::new()))
+    }
+
+    #[test]
+    fn names_wrap_the_inner_layout() {
+        let layout = Modified::new(Modifier::Gaps(8), Box::new(tile(Modifier::Mirror)));
+        assert_eq!(layout.name(), "gaps(8, mirror(tile))");
+        assert_eq!(tile(Modifier::Limit(2)).name(), "limit(2, tile)");
+    }
+
+    #[test]
+    fn mirror_stacks_instead() {
+        assert_eq!(
+            tile(Modifier::Mirror).arrange(&SCREEN, &windows(2)),
+            vec![
+                Geometry::new(100, 50, 1000, 400),
+                Geometry::new(100, 450, 1000, 400),
+            ]
+        );
+    }
+
+    #[test]
+    fn reflectx_flips_left_to_right() {
+        assert_eq!(
+            tile(Modifier::ReflectX).arrange(&SCREEN, &windows(2)),
+            vec![
+                Geometry::new(600, 50, 500, 800),
+                Geometry::new(100, 50, 500, 800),
+            ]
+        );
+    }
+
+    #[test]
+    fn reflecty_flips_top_to_bottom() {
+        assert_eq!(
+            tile(Modifier::ReflectY).arrange(&SCREEN, &windows(3)),
+            vec![
+                Geometry::new(100, 50, 500, 800),
+                Geometry::new(600, 450, 500, 400),
+                Geometry::new(600, 50, 500, 400),
+            ]
+        );
+    }
+
+    #[test]
+    fn gaps_are_even_everywhere() {
+        assert_eq!(
+            tile(Modifier::Gaps(10)).arrange(&SCREEN, &windows(2)),
+            vec![
+                Geometry::new(110, 60, 485, 780),
+                Geometry::new(605, 60, 485, 780),
+            ]
+        );
+    }
+
+    #[test]
+    fn smartborders_only_drops_a_single_border() {
+        let layout = tile(Modifier::SmartBorders);
+        assert!(!layout.borders(1));
+        assert!(layout.borders(2));
+        assert_eq!(
+            layout.arrange(&SCREEN, &windows(2)),
+            TileLayout::new().arrange(&SCREEN, &windows(2))
+        );
+
+        let nested = Modified::new(Modifier::Mirror, Box::new(layout));
+        assert!(!nested.borders(1));
+    }
+
+    #[test]
+    fn limit_moves_the_rest_off_screen() {
+        let hidden = Geometry::new(-1900, 50, 1000, 800);
+        assert_eq!(
+            tile(Modifier::Limit(2)).arrange(&SCREEN, &windows(3)),
+            vec![
+                Geometry::new(100, 50, 500, 800),
+                Geometry::new(600, 50, 500, 800),
+                hidden,
+            ]
+        );
+        assert_eq!(
+            tile(Modifier::Limit(5)).arrange(&SCREEN, &windows(2)),
+            TileLayout::new().arrange(&SCREEN, &windows(2))
+        );
+    }
+
+    #[test]
+    fn messages_reach_the_inner_layout() {
+        let clients = windows(2);
+        let mut layout = Modified::new(Modifier::Mirror, Box::new(BspLayout::new()));
+        layout.client_added(&clients[0], None);
+        layout.client_added(&clients[1], Some(&clients[0]));
+        assert!(layout.handle(&LayoutMessage::Rotate, Some(&clients[0])));
+        assert!(!tile(Modifier::Mirror).handle(&LayoutMessage::Rotate, Some(&clients[0])));
+    }
+
+    #[test]
+    fn limit_leaves_no_room_for_hidden_bsp_leaves() {
+        let clients = windows(3);
+        let mut layout = Modified::new(Modifier::Limit(2), Box::new(BspLayout::new()));
+        let mut prev = None;
+        for win in &clients {
+            layout.client_added(win, prev);
+            prev = Some(win);
+        }
+
+        assert_eq!(
+            layout.arrange(&SCREEN, &clients),
+            vec![
+                Geometry::new(100, 50, 500, 800),
+                Geometry::new(600, 50, 500, 800),
+                Geometry::new(-1900, 50, 1000, 800),
+            ]
+        );
+    }
+}
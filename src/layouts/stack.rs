@@ -23,35 +23,44 @@
  * SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
  */
 
-use std::collections::HashMap;
-
-use crate::core::layout::Layout;
+use crate::core::layout::{Geometry, Layout};
 use crate::core::x;
 
-pub struct StackLayout<'a> {
-    conn: &'a x::Connection,
-    master: HashMap<u32, x::Window>,
-    slave: HashMap<u32, x::Window>,
-    msize: u16,
-    ssize: u16,
-    space: u16,
+// Every client covers the whole screen. The focused one is raised on top.
+#[derive(Default)]
+pub struct StackLayout;
+
+impl StackLayout {
+    pub fn new() -> StackLayout {
+        StackLayout
+    }
 }
 
-impl<'a> Layout<'a> for StackLayout<'a> {
-    fn new(space: u16, conn: &'a x::Connection) -> TileLayout {
-        TileLayout {
-            conn: conn,
-            master: HashMap::new(),
-            slave: HashMap::new(),
-            msize: conn.get_screen_width(),
-            ssize: conn.get_screen_width(),
-            space: space,
-        }
+impl Layout for StackLayout {
+    fn name(&self) -> &str {
+        "stack"
     }
 
-    fn inc_msize(&mut self, size: u16) {}
+    fn inc_msize(&mut self, _size: i16) {}
+
+    fn inc_ssize(&mut self, _size: i16) {}
 
-    fn inc_ssize(&mut self, size: u16) {}
+    fn arrange(&self, screen: &Geometry, clients: &[x::Window]) -> Vec<Geometry> {
+        vec![*screen; clients.len()]
+    }
+}
 
-    fn render(&self) {}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layouts::{windows, SCREEN};
+
+    #[test]
+    fn all_cover_the_screen() {
+        assert_eq!(
+            StackLayout::new().arrange(&SCREEN, &windows(3)),
+            vec![SCREEN; 3]
+        );
+        assert_eq!(StackLayout::new().arrange(&SCREEN, &[]), vec![]);
+    }
 }
@@ -0,0 +1,128 @@
+/*
+ * Copyright (c) 2020, Florian Büstgens
+ * All rights reserved.
+ *
+ * Redistribution and use in source and binary forms, with or without
+ * modification, are permitted provided that the following conditions are met:
+ *     1. Redistributions of source code must retain the above copyright
+ *        notice, this list of conditions and the following disclaimer.
+ *
+ *     2. Redistributions in binary form must reproduce the above copyright notice,
+ *        this list of conditions and the following disclaimer in the
+ *        documentation and/or other materials provided with the distribution.
+ *
+ * THIS SOFTWARE IS PROVIDED BY <copyright holder> ''AS IS'' AND ANY
+ * EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+ * WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+ * DISCLAIMED. IN NO EVENT SHALL <copyright holder> BE LIABLE FOR ANY
+ * DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+ * (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+ * LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND
+ * ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+ * (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+ * SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+ */
+
+use crate::core::layout::{Geometry, Layout};
+use crate::core::x;
+use crate::layouts::resize_msize;
+
+// Master column on the left, the stack is split into two columns of equal
+// width next to it.
+pub struct ThreeColumnLayout {
+    nmaster: usize,
+    msize: u16,
+}
+
+impl ThreeColumnLayout {
+    pub fn new() -> ThreeColumnLayout {
+        ThreeColumnLayout {
+            nmaster: 1,
+            msize: 34,
+        }
+    }
+}
+
+impl Layout for ThreeColumnLayout {
+    fn name(&self) -> &str {
+        "threecol"
+    }
+
+    fn inc_msize(&mut self, size: i16) {
+        self.msize = resize_msize(self.msize, size);
+    }
+
+    fn inc_ssize(&mut self, size: i16) {
+        self.msize = resize_msize(self.msize, -size);
+    }
+
+    fn inc_nmaster(&mut self, count: i16) {
+        self.nmaster = (self.nmaster as i32 + i32::from(count)).max(0) as usize;
+    }
+
+    fn arrange(&self, screen: &Geometry, clients: &[x::Window]) -> Vec<Geometry> {
+        let count = clients.len();
+        let nmaster = self.nmaster.min(count);
+        let nstack = count - nmaster;
+
+        if nmaster == 0 || nstack == 0 {
+            return screen.rows(count);
+        }
+
+        let (master, stack) = screen.divide_columns(self.msize);
+
+        let mut geos = master.rows(nmaster);
+        if nstack == 1 {
+            geos.push(stack);
+            return geos;
+        }
+
+        let cols = stack.columns(2);
+        let nmiddle = nstack - nstack / 2;
+        geos.extend(cols[0].rows(nmiddle));
+        geos.extend(cols[1].rows(nstack - nmiddle));
+        geos
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layouts::{windows, SCREEN};
+
+    #[test]
+    fn stack_in_two_columns() {
+        assert_eq!(
+            ThreeColumnLayout::new().arrange(&SCREEN, &windows(4)),
+            vec![
+                Geometry::new(0, 0, 340, 800),
+                Geometry::new(340, 0, 330, 400),
+                Geometry::new(340, 400, 330, 400),
+                Geometry::new(670, 0, 330, 800),
+            ]
+        );
+    }
+
+    #[test]
+    fn single_stack_client_takes_both_columns() {
+        assert_eq!(
+            ThreeColumnLayout::new().arrange(&SCREEN, &windows(2)),
+            vec![
+                Geometry::new(0, 0, 340, 800),
+                Geometry::new(340, 0, 660, 800)
+            ]
+        );
+    }
+
+    #[test]
+    fn rows_without_stack() {
+        assert_eq!(
+            ThreeColumnLayout::new().arrange(&SCREEN, &windows(1)),
+            vec![SCREEN]
+        );
+
+        let mut layout = ThreeColumnLayout::new();
+        layout.inc_nmaster(-1);
+        assert_eq!(layout.arrange(&SCREEN, &windows(2)), SCREEN.rows(2));
+    }
+}
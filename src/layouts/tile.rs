@@ -23,39 +23,109 @@
  * SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
  */
 
-use std::collections::HashMap;
-
-use crate::core::layout::Layout;
+use crate::core::layout::{Geometry, Layout};
 use crate::core::x;
+use crate::layouts::resize_msize;
 
-pub struct TileLayout<'a> {
-    conn: &'a x::Connection,
-    master: HashMap<u32, x::Window>,
-    slave: HashMap<u32, x::Window>,
+pub struct TileLayout {
+    nmaster: usize,
     msize: u16,
-    ssize: u16,
-    space: u16,
 }
 
-impl<'a> Layout<'a> for TileLayout<'a> {
-    fn new(space: u16, conn: &'a x::Connection) -> TileLayout {
+impl TileLayout {
+    pub fn new() -> TileLayout {
         TileLayout {
-            conn: conn,
-            master: HashMap::new(),
-            slave: HashMap::new(),
-            msize: conn.get_screen_width() / 2,
-            ssize: conn.get_screen_width() / 2,
-            space: space,
+            nmaster: 1,
+            msize: 50,
         }
     }
+}
+
+impl Layout for TileLayout {
+    fn name(&self) -> &str {
+        "tile"
+    }
+
+    fn inc_msize(&mut self, size: i16) {
+        self.msize = resize_msize(self.msize, size);
+    }
 
-    fn inc_msize(&mut self, size: u16) {
-        self.msize += size;
+    // The stack takes whatever the master leaves.
+    fn inc_ssize(&mut self, size: i16) {
+        self.msize = resize_msize(self.msize, -size);
     }
 
-    fn inc_ssize(&mut self, size: u16) {
-        self.ssize += size;
+    fn inc_nmaster(&mut self, count: i16) {
+        self.nmaster = (self.nmaster as i32 + i32::from(count)).max(0) as usize;
     }
 
-    fn render(&self) {}
+    fn arrange(&self, screen: &Geometry, clients: &[x::Window]) -> Vec<Geometry> {
+        let count = clients.len();
+        let nmaster = self.nmaster.min(count);
+        let nstack = count - nmaster;
+
+        if nmaster == 0 || nstack == 0 {
+            return screen.rows(count);
+        }
+
+        let (master, stack) = screen.divide_columns(self.msize);
+
+        let mut geos = master.rows(nmaster);
+        geos.extend(stack.rows(nstack));
+        geos
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layouts::{windows, SCREEN};
+
+    #[test]
+    fn master_and_stack() {
+        assert_eq!(
+            TileLayout::new().arrange(&SCREEN, &windows(3)),
+            vec![
+                Geometry::new(0, 0, 500, 800),
+                Geometry::new(500, 0, 500, 400),
+                Geometry::new(500, 400, 500, 400),
+            ]
+        );
+    }
+
+    #[test]
+    fn rows_without_master() {
+        let mut layout = TileLayout::new();
+        layout.inc_nmaster(-1);
+        assert_eq!(
+            layout.arrange(&SCREEN, &windows(3)),
+            vec![
+                Geometry::new(0, 0, 1000, 266),
+                Geometry::new(0, 266, 1000, 266),
+                Geometry::new(0, 532, 1000, 268),
+            ]
+        );
+    }
+
+    #[test]
+    fn rows_when_all_are_masters() {
+        let mut layout = TileLayout::new();
+        layout.inc_nmaster(4);
+        assert_eq!(layout.arrange(&SCREEN, &windows(3)), SCREEN.rows(3));
+        assert_eq!(layout.arrange(&SCREEN, &windows(1)), vec![SCREEN]);
+        assert_eq!(layout.arrange(&SCREEN, &[]), vec![]);
+    }
+
+    #[test]
+    fn msize_moves_the_split() {
+        let mut layout = TileLayout::new();
+        layout.inc_msize(10);
+        assert_eq!(
+            layout.arrange(&SCREEN, &windows(2)),
+            vec![
+                Geometry::new(0, 0, 600, 800),
+                Geometry::new(600, 0, 400, 800)
+            ]
+        );
+    }
 }
@@ -26,10 +26,16 @@ mod config;
 mod core;
 mod layouts;
 
+use crate::core::client::ClientManager;
 use crate::core::event::{Event, EventLoop};
-use crate::core::keys::{KeyCombo, KeyHandlers};
+use crate::core::command::{Action, Commands};
+use crate::core::signal;
 use crate::core::x::Connection;
 use crate::core::x::Window;
+use std::env;
+use std::os::unix::process::CommandExt;
+use std::path::PathBuf;
+use std::process::{self, Command};
 
 #[allow(dead_code)]
 #[cfg(target_os = "linux")]
@@ -46,6 +52,8 @@ mod _restr {
     pub use pledge::{pledge, pledge_execpromises, pledge_promises};
 }
 
+// Only the BSDs restrict anything.
+#[allow(unused_imports)]
 use _restr::*;
 use crate::config::Config;
 
@@ -60,39 +68,177 @@ fn _sandbox() {
 
 #[cfg(target_os = "openbsd")]
 fn _sandbox() {
-    pledge_promises![Stdio Exec].unwrap();
+    pledge_promises![Stdio Rpath Exec].unwrap();
 }
 
 fn main() {
     // Setup
     _sandbox();
 
+    let args: Vec<String> = env::args().collect();
+    if args.get(1).map(String::as_str) == Some("--check-config") {
+        process::exit(_check_config(args.get(2)));
+    }
+
+    if _run() {
+        _restart();
+    }
+}
+
+// Returns whether to start over.
+fn _run() -> bool {
     // Startup
-    let mut conn: Connection = Connection::open().expect("[E] Could not open connection");
+    let conn: Connection = Connection::open().expect("[E] Could not open connection");
     let mut event_conn: EventLoop = conn.get_event_loop();
-    let conf: Config = config::Config::new();
-    let keys: KeyHandlers = core::keys::KeyHandlers::new();
+    let mut conf: Config = config::Config::load();
+    let mut clients = ClientManager::new(&conn, &conf);
 
-    conn.check_wm(&keys).expect("[E] WM is already running.");
-    conf.wire();
+    conn.check_wm(conf.get_ctrl().key_handlers())
+        .expect("[E] WM is already running.");
 
     let exist_win: Vec<Window> = conn
         .top_level_windows()
         .expect("[E] Could not determine existing windows.");
 
-    for event in event_conn {
+    clients.adopt(exist_win);
+
+    if let Err(err) = signal::catch_hangup() {
+        println!("{}", err);
+    }
+    signal::reap_children();
+
+    let mut restart = false;
+    while let Some(event) = event_conn.next() {
         match event {
-            Event::MapRequest(window_id) => _map_request(window_id),
-            Event::UnmapNotify(window_id) => _unmap_notify(&window_id),
-            Event::DestroyNotify(window_id) => _destroy_notify(&window_id),
-            Event::KeyPress(key) => _key_press(key),
-            Event::EnterNotify(window_id) => _enter_notify(&window_id),
+            // These re-render, like the key actions below.
+            Event::MapRequest(window_id) => {
+                _map_request(&mut clients, window_id);
+                event_conn.discard_enter_events();
+            }
+            Event::UnmapNotify(window_id) => {
+                _unmap_notify(&mut clients, &window_id);
+                event_conn.discard_enter_events();
+            }
+            Event::Withdrawn(window_id) => {
+                _withdrawn(&mut clients, &window_id);
+                event_conn.discard_enter_events();
+            }
+            Event::DestroyNotify(window_id) => {
+                _destroy_notify(&mut clients, &window_id);
+                event_conn.discard_enter_events();
+            }
+            Event::KeyPress(key) | Event::KeyRelease(key) => {
+                let mode = conf.get_ctrl().mode().map(String::from);
+                match conf.get_ctrl_mut().dispatch(&key) {
+                    Some(Action::Quit) => break,
+                    Some(Action::Restart) => {
+                        restart = true;
+                        break;
+                    }
+                    Some(Action::Reload) => _reload(&conn, &mut conf, &mut clients),
+                    Some(action) => {
+                        action.run(&mut clients);
+                        event_conn.discard_enter_events();
+                    }
+                    None => {}
+                }
+
+                let ctrl = conf.get_ctrl();
+                if ctrl.mode() != mode.as_deref() {
+                    _mode_changed(&conn, ctrl.mode());
+                }
+                event_conn.set_timeout(ctrl.mode().map(|_| conf.get_mode_timeout()));
+            }
+            Event::EnterNotify(window_id) => _enter_notify(&mut clients, &window_id),
+            Event::PropertyNotify(window_id) => _property_notify(&mut clients, &window_id),
+            Event::MappingNotify => _mapping_notify(&conn, conf.get_ctrl()),
+            Event::Timeout => {
+                conf.get_ctrl_mut().leave_mode();
+                _mode_changed(&conn, None);
+            }
+            Event::Hangup => {
+                let mode = conf.get_ctrl().mode().is_some();
+                _reload(&conn, &mut conf, &mut clients);
+                if mode && conf.get_ctrl().mode().is_none() {
+                    _mode_changed(&conn, None);
+                    event_conn.set_timeout(None);
+                }
+            }
         };
     }
+
+    clients.release();
+    restart
+}
+
+// Parse the config file without touching X. Returns the exit status.
+fn _check_config(path: Option<&String>) -> i32 {
+    let path = path.map_or_else(Config::path, PathBuf::from);
+    match Config::from_file(&path) {
+        Ok(_) => {
+            println!("{}: ok", path.display());
+            0
+        }
+        Err(err) => {
+            println!("{}", err);
+            1
+        }
+    }
+}
+
+// Replace ourselves with a fresh instance, the X connection is closed by now.
+fn _restart() {
+    let args: Vec<String> = env::args().collect();
+    let err = Command::new(&args[0]).args(&args[1..]).exec();
+    println!("[E] Could not restart: {}", err);
+}
+
+fn _map_request(clients: &mut ClientManager, win: Window) {
+    clients.manage(win);
+}
+
+fn _unmap_notify(clients: &mut ClientManager, win: &Window) {
+    clients.withdraw(win);
+}
+
+fn _withdrawn(clients: &mut ClientManager, win: &Window) {
+    clients.unmanage(win);
+}
+
+fn _destroy_notify(clients: &mut ClientManager, win: &Window) {
+    clients.forget(win);
+}
+
+fn _enter_notify(clients: &mut ClientManager, win: &Window) {
+    clients.focus(win);
+}
+
+fn _property_notify(clients: &mut ClientManager, win: &Window) {
+    clients.update_urgency(win);
+}
+
+// Keep the keyboard while a mode is active and tell the status bar.
+fn _mode_changed(conn: &Connection, mode: Option<&str>) {
+    match mode {
+        Some(_) => conn.grab_keyboard(),
+        None => conn.ungrab_keyboard(),
+    }
+    conn.set_mode(mode);
 }
 
-fn _map_request(win: Window) {}
-fn _unmap_notify(win: &Window) {}
-fn _destroy_notify(win: &Window) {}
-fn _key_press(key: KeyCombo) {}
-fn _enter_notify(win: &Window) {}
+// A broken config file leaves the running one alone. The new config
+// starts without an active mode.
+fn _reload(conn: &Connection, conf: &mut Config, clients: &mut ClientManager) {
+    match Config::try_load() {
+        Ok(new) => {
+            conn.regrab_keys(conf.get_ctrl().key_handlers(), new.get_ctrl().key_handlers());
+            clients.reconfigure(&new);
+            *conf = new;
+        }
+        Err(err) => println!("{}\n[E] Keeping the current config.", err),
+    }
+}
+
+fn _mapping_notify(conn: &Connection, ctrl: &Commands) {
+    conn.grab_keys(ctrl.key_handlers());
+}